@@ -0,0 +1,364 @@
+//! Minimal editor for Android's compiled binary XML (`AndroidManifest.xml` inside of an apk)
+//!
+//! Only attribute values are edited. New strings are appended to the string pool so the
+//! resource map, which lines up with the start of the pool, stays valid.
+
+const RES_STRING_POOL_TYPE: u16 = 0x0001;
+const RES_XML_TYPE: u16 = 0x0003;
+const RES_XML_START_ELEMENT_TYPE: u16 = 0x0102;
+
+const UTF8_FLAG: u32 = 1 << 8;
+
+const TYPE_STRING: u8 = 0x03;
+const TYPE_INT_DEC: u8 = 0x10;
+
+/// Index used when an attribute has no raw string value
+const NO_ENTRY: u32 = 0xFFFF_FFFF;
+
+fn u16_at(data: &[u8], at: usize) -> anyhow::Result<u16> {
+    data.get(at..at + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or(anyhow::anyhow!("binary xml is truncated"))
+}
+
+fn u32_at(data: &[u8], at: usize) -> anyhow::Result<u32> {
+    data.get(at..at + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(anyhow::anyhow!("binary xml is truncated"))
+}
+
+fn put_u32(data: &mut [u8], at: usize, value: u32) {
+    data[at..at + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+struct StringPool {
+    strings: Vec<String>,
+    utf8: bool,
+    flags: u32,
+    /// Style offsets followed by the style data, copied as is
+    style_offsets: Vec<u32>,
+    styles: Vec<u8>,
+}
+
+impl StringPool {
+    fn parse(chunk: &[u8]) -> anyhow::Result<Self> {
+        let header_size = u16_at(chunk, 2)? as usize;
+        let string_count = u32_at(chunk, 8)? as usize;
+        let style_count = u32_at(chunk, 12)? as usize;
+        let flags = u32_at(chunk, 16)?;
+        let strings_start = u32_at(chunk, 20)? as usize;
+        let styles_start = u32_at(chunk, 24)? as usize;
+        let utf8 = flags & UTF8_FLAG != 0;
+
+        let mut strings = Vec::with_capacity(string_count);
+        for i in 0..string_count {
+            let offset = strings_start + u32_at(chunk, header_size + i * 4)? as usize;
+            strings.push(if utf8 {
+                decode_utf8(chunk, offset)?
+            } else {
+                decode_utf16(chunk, offset)?
+            });
+        }
+
+        let style_offsets = (0..style_count)
+            .map(|i| u32_at(chunk, header_size + (string_count + i) * 4))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let styles = if style_count > 0 {
+            chunk[styles_start..].to_vec()
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            strings,
+            utf8,
+            flags,
+            style_offsets,
+            styles,
+        })
+    }
+
+    /// Index of `value`, appending it to the pool if it doesn't exist
+    fn index(&mut self, value: &str) -> u32 {
+        match self.strings.iter().position(|s| s == value) {
+            Some(index) => index as u32,
+            None => {
+                self.strings.push(value.to_string());
+                (self.strings.len() - 1) as u32
+            }
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let header_size = 28usize;
+
+        let mut data = Vec::new();
+        let mut offsets = Vec::with_capacity(self.strings.len());
+        for string in self.strings.iter() {
+            offsets.push(data.len() as u32);
+            if self.utf8 {
+                encode_utf8(&mut data, string);
+            } else {
+                encode_utf16(&mut data, string);
+            }
+        }
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+
+        let strings_start = header_size + (self.strings.len() + self.style_offsets.len()) * 4;
+        let styles_start = if self.style_offsets.is_empty() {
+            0
+        } else {
+            strings_start + data.len()
+        };
+
+        let mut chunk = Vec::new();
+        chunk.extend(RES_STRING_POOL_TYPE.to_le_bytes());
+        chunk.extend((header_size as u16).to_le_bytes());
+        chunk.extend(0u32.to_le_bytes());
+        chunk.extend((self.strings.len() as u32).to_le_bytes());
+        chunk.extend((self.style_offsets.len() as u32).to_le_bytes());
+        // The pool is no longer sorted once strings are appended
+        chunk.extend((self.flags & !1).to_le_bytes());
+        chunk.extend((strings_start as u32).to_le_bytes());
+        chunk.extend((styles_start as u32).to_le_bytes());
+        for offset in offsets.iter().chain(self.style_offsets.iter()) {
+            chunk.extend(offset.to_le_bytes());
+        }
+        chunk.extend(data);
+        chunk.extend(self.styles.iter());
+
+        let size = chunk.len() as u32;
+        put_u32(&mut chunk, 4, size);
+        chunk
+    }
+}
+
+fn decode_utf16(chunk: &[u8], offset: usize) -> anyhow::Result<String> {
+    let mut len = u16_at(chunk, offset)? as usize;
+    let mut start = offset + 2;
+    if len & 0x8000 != 0 {
+        len = ((len & 0x7FFF) << 16) | u16_at(chunk, start)? as usize;
+        start += 2;
+    }
+
+    let units = (0..len)
+        .map(|i| u16_at(chunk, start + i * 2))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(String::from_utf16_lossy(&units))
+}
+
+fn decode_utf8(chunk: &[u8], offset: usize) -> anyhow::Result<String> {
+    let byte = |at: usize| {
+        chunk
+            .get(at)
+            .copied()
+            .ok_or(anyhow::anyhow!("binary xml is truncated"))
+    };
+    let length = |at: &mut usize| -> anyhow::Result<usize> {
+        let first = byte(*at)? as usize;
+        *at += 1;
+        if first & 0x80 != 0 {
+            let second = byte(*at)? as usize;
+            *at += 1;
+            Ok(((first & 0x7F) << 8) | second)
+        } else {
+            Ok(first)
+        }
+    };
+
+    let mut at = offset;
+    // Length in utf16 units followed by the length in bytes
+    length(&mut at)?;
+    let len = length(&mut at)?;
+    let bytes = chunk
+        .get(at..at + len)
+        .ok_or(anyhow::anyhow!("binary xml is truncated"))?;
+    Ok(String::from_utf8_lossy(bytes).to_string())
+}
+
+fn encode_utf16(data: &mut Vec<u8>, value: &str) {
+    let units = value.encode_utf16().collect::<Vec<_>>();
+    if units.len() > 0x7FFF {
+        data.extend((((units.len() >> 16) as u16) | 0x8000).to_le_bytes());
+        data.extend((units.len() as u16).to_le_bytes());
+    } else {
+        data.extend((units.len() as u16).to_le_bytes());
+    }
+    for unit in units {
+        data.extend(unit.to_le_bytes());
+    }
+    data.extend([0, 0]);
+}
+
+fn encode_utf8(data: &mut Vec<u8>, value: &str) {
+    let length = |data: &mut Vec<u8>, len: usize| {
+        if len > 0x7F {
+            data.push(((len >> 8) as u8) | 0x80);
+        }
+        data.push(len as u8);
+    };
+
+    length(data, value.encode_utf16().count());
+    length(data, value.len());
+    data.extend(value.as_bytes());
+    data.push(0);
+}
+
+/// Attribute of an element that is being edited
+pub struct Attribute<'a> {
+    data: &'a mut [u8],
+}
+
+impl Attribute<'_> {
+    fn raw_value(&self) -> u32 {
+        u32::from_le_bytes(self.data[8..12].try_into().unwrap())
+    }
+
+    fn set(&mut self, raw_value: u32, data_type: u8, data: u32) {
+        put_u32(self.data, 8, raw_value);
+        self.data[15] = data_type;
+        put_u32(self.data, 16, data);
+    }
+}
+
+/// Compiled binary xml document
+pub struct BinaryXml {
+    pool: StringPool,
+    /// Chunks that come after the string pool
+    chunks: Vec<u8>,
+}
+
+impl BinaryXml {
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        if u16_at(data, 0)? != RES_XML_TYPE {
+            return Err(anyhow::anyhow!("not a compiled binary xml document"));
+        }
+
+        let header_size = u16_at(data, 2)? as usize;
+        if u16_at(data, header_size)? != RES_STRING_POOL_TYPE {
+            return Err(anyhow::anyhow!("binary xml is missing it's string pool"));
+        }
+
+        let pool_size = u32_at(data, header_size + 4)? as usize;
+        let pool_end = header_size + pool_size;
+        let pool = StringPool::parse(
+            data.get(header_size..pool_end)
+                .ok_or(anyhow::anyhow!("binary xml is truncated"))?,
+        )?;
+
+        Ok(Self {
+            pool,
+            chunks: data[pool_end..].to_vec(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let pool = self.pool.to_bytes();
+
+        let mut data = Vec::with_capacity(8 + pool.len() + self.chunks.len());
+        data.extend(RES_XML_TYPE.to_le_bytes());
+        data.extend(8u16.to_le_bytes());
+        data.extend(((8 + pool.len() + self.chunks.len()) as u32).to_le_bytes());
+        data.extend(pool);
+        data.extend(self.chunks.iter());
+        data
+    }
+
+    /// Call `f` with every attribute `name` on every `element`
+    ///
+    /// Returns the number of attributes that were visited
+    fn each_attribute(
+        &mut self,
+        element: &str,
+        name: &str,
+        mut f: impl FnMut(&mut Attribute, &mut StringPool),
+    ) -> anyhow::Result<usize> {
+        let mut count = 0;
+        let mut at = 0;
+
+        while at + 8 <= self.chunks.len() {
+            let ty = u16_at(&self.chunks, at)?;
+            let header_size = u16_at(&self.chunks, at + 2)? as usize;
+            let size = u32_at(&self.chunks, at + 4)? as usize;
+            if size < 8 || at + size > self.chunks.len() {
+                return Err(anyhow::anyhow!("binary xml has an invalid chunk size"));
+            }
+
+            if ty == RES_XML_START_ELEMENT_TYPE {
+                let ext = at + header_size;
+                let element_name = u32_at(&self.chunks, ext + 4)? as usize;
+
+                if self.pool.strings.get(element_name).map(String::as_str) == Some(element) {
+                    let attribute_start = u16_at(&self.chunks, ext + 8)? as usize;
+                    let attribute_size = u16_at(&self.chunks, ext + 10)? as usize;
+                    let attribute_count = u16_at(&self.chunks, ext + 12)? as usize;
+
+                    for i in 0..attribute_count {
+                        let offset = ext + attribute_start + i * attribute_size;
+                        let attribute_name = u32_at(&self.chunks, offset + 4)? as usize;
+
+                        if self.pool.strings.get(attribute_name).map(String::as_str) == Some(name) {
+                            let mut attribute = Attribute {
+                                data: &mut self.chunks[offset..offset + attribute_size],
+                            };
+                            f(&mut attribute, &mut self.pool);
+                            count += 1;
+                        }
+                    }
+                }
+            }
+
+            at += size;
+        }
+
+        Ok(count)
+    }
+
+    /// String value of the first attribute `name` on `element`
+    pub fn string(&mut self, element: &str, name: &str) -> anyhow::Result<Option<String>> {
+        let mut value = None;
+        self.each_attribute(element, name, |attribute, pool| {
+            if value.is_none() {
+                value = pool.strings.get(attribute.raw_value() as usize).cloned();
+            }
+        })?;
+        Ok(value)
+    }
+
+    /// Set attribute `name` on every `element` to a literal string
+    pub fn set_string(&mut self, element: &str, name: &str, value: &str) -> anyhow::Result<usize> {
+        self.each_attribute(element, name, |attribute, pool| {
+            let index = pool.index(value);
+            attribute.set(index, TYPE_STRING, index);
+        })
+    }
+
+    /// Set attribute `name` on every `element` to an integer
+    pub fn set_int(&mut self, element: &str, name: &str, value: u32) -> anyhow::Result<usize> {
+        self.each_attribute(element, name, |attribute, _| {
+            attribute.set(NO_ENTRY, TYPE_INT_DEC, value);
+        })
+    }
+
+    /// Replace the `from` prefix of string attribute `name` on every `element`
+    pub fn replace_prefix(
+        &mut self,
+        element: &str,
+        name: &str,
+        from: &str,
+        to: &str,
+    ) -> anyhow::Result<usize> {
+        self.each_attribute(element, name, |attribute, pool| {
+            let Some(current) = pool.strings.get(attribute.raw_value() as usize) else {
+                return;
+            };
+
+            if let Some(rest) = current.strip_prefix(from) {
+                let index = pool.index(&format!("{to}{rest}"));
+                attribute.set(index, TYPE_STRING, index);
+            }
+        })
+    }
+}
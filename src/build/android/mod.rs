@@ -0,0 +1,186 @@
+use std::{
+    fs::File,
+    io::{BufReader, Cursor, Read, Write},
+    path::Path,
+};
+
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+mod axml;
+mod sign;
+
+use axml::BinaryXml;
+pub use sign::Signer;
+
+/// Path of the game inside of the apk that love-android runs on launch
+const GAME: &str = "assets/game.love";
+const MANIFEST: &str = "AndroidManifest.xml";
+
+/// Changes applied to the framework's apk
+pub struct Customizations<'a> {
+    /// Application id / package name
+    pub package: &'a str,
+    /// Name shown under the app icon
+    pub label: &'a str,
+    pub version_code: u32,
+    pub version_name: &'a str,
+    /// Compressed game archive
+    pub game: &'a [u8],
+    /// Png used in place of the framework's launcher icons
+    pub icon: Option<&'a [u8]>,
+}
+
+/// Framework apk that the game is embedded into
+pub struct Apk {
+    archive: ZipArchive<BufReader<File>>,
+}
+
+impl Apk {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        Ok(Self {
+            archive: ZipArchive::new(BufReader::new(
+                File::open(path)
+                    .map_err(|e| anyhow::anyhow!("failed to open {}: {e}", path.display()))?,
+            ))?,
+        })
+    }
+
+    fn read(&mut self, name: &str) -> anyhow::Result<Vec<u8>> {
+        let mut content = Vec::new();
+        self.archive.by_name(name)?.read_to_end(&mut content)?;
+        Ok(content)
+    }
+
+    /// Rewrite the manifest for the game's identity
+    fn manifest(&mut self, customizations: &Customizations) -> anyhow::Result<Vec<u8>> {
+        let mut manifest = BinaryXml::parse(&self.read(MANIFEST)?)?;
+
+        let package = manifest
+            .string("manifest", "package")?
+            .ok_or(anyhow::anyhow!("apk manifest has no package name"))?;
+        manifest.set_string("manifest", "package", customizations.package)?;
+
+        // Providers and permissions are namespaced by the package and must be unique per
+        // device, otherwise the game conflicts with the framework when both are installed
+        let from = format!("{package}.");
+        let to = format!("{}.", customizations.package);
+        manifest.replace_prefix("provider", "authorities", &from, &to)?;
+        manifest.replace_prefix("permission", "name", &from, &to)?;
+        manifest.replace_prefix("uses-permission", "name", &from, &to)?;
+
+        manifest.set_string("application", "label", customizations.label)?;
+        manifest.set_string("activity", "label", customizations.label)?;
+        manifest.set_int("manifest", "versionCode", customizations.version_code)?;
+        manifest.set_string("manifest", "versionName", customizations.version_name)?;
+
+        Ok(manifest.to_bytes())
+    }
+
+    /// Write the customized apk to `out`, zipaligned and signed with `signer`
+    pub fn write(
+        &mut self,
+        out: &Path,
+        customizations: &Customizations,
+        signer: &Signer,
+    ) -> anyhow::Result<()> {
+        let manifest = self.manifest(customizations)?;
+
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        // Name and uncompressed content of every entry for the v1 signature
+        let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
+
+        let mut replaced = false;
+        for i in 0..self.archive.len() {
+            let name = self
+                .archive
+                .name_for_index(i)
+                .unwrap_or_default()
+                .to_string();
+            if name.ends_with('/')
+                || sign::is_signature_file(&name)
+                || name == MANIFEST
+                || name == GAME
+            {
+                continue;
+            }
+
+            let mut content = Vec::new();
+            let method = {
+                let mut file = self.archive.by_index(i)?;
+                file.read_to_end(&mut content)?;
+                file.compression()
+            };
+
+            if let (true, Some(icon)) = (is_icon(&name), customizations.icon) {
+                replaced = true;
+                writer.start_file(name.as_str(), stored(&name))?;
+                writer.write_all(icon)?;
+                entries.push((name, icon.to_vec()));
+                continue;
+            }
+
+            if method == CompressionMethod::Stored {
+                // Uncompressed entries are aligned so they can be memory mapped
+                writer.start_file(name.as_str(), stored(&name))?;
+                writer.write_all(&content)?;
+            } else {
+                writer.raw_copy_file_rename(self.archive.by_index_raw(i)?, name.as_str())?;
+            }
+            entries.push((name, content));
+        }
+
+        if customizations.icon.is_some() && !replaced {
+            return Err(anyhow::anyhow!(
+                "no launcher icons found in the framework apk"
+            ));
+        }
+
+        writer.start_file(MANIFEST, deflated())?;
+        writer.write_all(&manifest)?;
+        entries.push((MANIFEST.to_string(), manifest));
+
+        writer.start_file(GAME, stored(GAME))?;
+        writer.write_all(customizations.game)?;
+        entries.push((GAME.to_string(), customizations.game.to_vec()));
+
+        let [mf, sf, rsa] = signer.v1(entries
+            .iter()
+            .map(|(name, content)| (name.as_str(), content.as_slice())))?;
+        for (name, content) in [
+            ("META-INF/MANIFEST.MF", mf),
+            ("META-INF/CERT.SF", sf),
+            ("META-INF/CERT.RSA", rsa),
+        ] {
+            writer.start_file(name, deflated())?;
+            writer.write_all(&content)?;
+        }
+
+        let apk = writer.finish()?.into_inner();
+        std::fs::write(out, signer.v2(&apk)?)?;
+
+        Ok(())
+    }
+}
+
+fn deflated() -> SimpleFileOptions {
+    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated)
+}
+
+/// Options for an uncompressed entry, aligned the same way `zipalign -p` aligns them
+fn stored(name: &str) -> SimpleFileOptions {
+    SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .with_alignment(if name.ends_with(".so") { 4096 } else { 4 })
+}
+
+/// Whether the entry is one of love-android's launcher icons
+///
+/// The manifest only references the icon by resource id so the icons are matched by name
+fn is_icon(name: &str) -> bool {
+    let Some(file) = name.strip_prefix("res/").and_then(|v| v.rsplit('/').next()) else {
+        return false;
+    };
+
+    file.ends_with(".png") && (file == "love.png" || file.starts_with("ic_launcher"))
+}
@@ -0,0 +1,382 @@
+//! APK signing with the v1 (jar) and v2 signature schemes
+//!
+//! - v1: https://source.android.com/docs/security/features/apksigning#v1
+//! - v2: https://source.android.com/docs/security/features/apksigning/v2
+
+use base64::Engine;
+use p12_keystore::{KeyStore, KeyStoreEntry, Pkcs12ImportPolicy};
+use rsa::{
+    pkcs1v15::SigningKey,
+    pkcs8::DecodePrivateKey,
+    signature::{SignatureEncoding, Signer as _},
+    RsaPrivateKey,
+};
+use sha2::{Digest, Sha256};
+
+const APK_SIGNATURE_SCHEME_V2_ID: u32 = 0x7109_871a;
+const SIGNATURE_RSA_PKCS1_V1_5_WITH_SHA256: u32 = 0x0103;
+const APK_SIG_BLOCK_MAGIC: &[u8; 16] = b"APK Sig Block 42";
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+
+const OID_SIGNED_DATA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02];
+const OID_DATA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x01];
+const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x01];
+
+/// Private key and certificate used to sign an apk
+pub struct Signer {
+    key: SigningKey<Sha256>,
+    certificate: Vec<u8>,
+}
+
+impl Signer {
+    /// Load the key `alias` from a PKCS#12 keystore, or the first key if no alias is given
+    pub fn from_pkcs12(data: &[u8], password: &str, alias: Option<&str>) -> anyhow::Result<Self> {
+        let keystore = KeyStore::from_pkcs12(data, password, Pkcs12ImportPolicy::Relaxed)
+            .map_err(|e| anyhow::anyhow!("failed to open keystore: {e}"))?;
+
+        let chain = match alias {
+            Some(alias) => match keystore.entry(alias) {
+                Some(KeyStoreEntry::PrivateKeyChain(chain)) => chain,
+                _ => return Err(anyhow::anyhow!("keystore has no private key `{alias}`")),
+            },
+            None => {
+                keystore
+                    .private_key_chain()
+                    .ok_or(anyhow::anyhow!("keystore has no private key"))?
+                    .1
+            }
+        };
+
+        let certificate = chain
+            .certs()
+            .first()
+            .ok_or(anyhow::anyhow!("keystore key has no certificate"))?
+            .as_der()
+            .to_vec();
+
+        let key = RsaPrivateKey::from_pkcs8_der(chain.key().as_der())
+            .map_err(|_| anyhow::anyhow!("only RSA keys are supported for signing"))?;
+
+        Ok(Self {
+            key: SigningKey::new(key),
+            certificate,
+        })
+    }
+
+    fn sign(&self, data: &[u8]) -> Vec<u8> {
+        self.key.sign(data).to_vec()
+    }
+
+    /// `META-INF/MANIFEST.MF`, `META-INF/CERT.SF`, and `META-INF/CERT.RSA` for the v1 scheme
+    /// from the name and content of every entry
+    pub fn v1<'a>(
+        &self,
+        entries: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+    ) -> anyhow::Result<[Vec<u8>; 3]> {
+        let mut manifest = String::new();
+        manifest.push_str("Manifest-Version: 1.0\r\n");
+        manifest.push_str("Created-By: 1.0 (lbt)\r\n");
+        manifest.push_str("\r\n");
+
+        let mut sections = String::new();
+        for (name, content) in entries {
+            let section = format!(
+                "{}SHA-256-Digest: {}\r\n\r\n",
+                header_line("Name", name),
+                digest(content)
+            );
+            sections.push_str(&header_line("Name", name));
+            sections.push_str(&format!(
+                "SHA-256-Digest: {}\r\n\r\n",
+                digest(section.as_bytes())
+            ));
+            manifest.push_str(&section);
+        }
+
+        let mut sf = String::new();
+        sf.push_str("Signature-Version: 1.0\r\n");
+        sf.push_str("Created-By: 1.0 (lbt)\r\n");
+        sf.push_str(&format!(
+            "SHA-256-Digest-Manifest: {}\r\n",
+            digest(manifest.as_bytes())
+        ));
+        // Rejects the apk if the v2 signature was stripped
+        sf.push_str("X-Android-APK-Signed: 2\r\n");
+        sf.push_str("\r\n");
+        sf.push_str(&sections);
+
+        let signature = self.pkcs7(&self.sign(sf.as_bytes()))?;
+        Ok([manifest.into_bytes(), sf.into_bytes(), signature])
+    }
+
+    /// PKCS#7 `SignedData` with a detached signature over `CERT.SF`
+    fn pkcs7(&self, signature: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let tbs = Der::children(&self.certificate)?
+            .into_iter()
+            .next()
+            .ok_or(anyhow::anyhow!("invalid certificate"))?;
+        let mut fields = Der::children(tbs)?.into_iter();
+        let mut serial = fields
+            .next()
+            .ok_or(anyhow::anyhow!("invalid certificate"))?;
+        // Skip the explicit version
+        if serial[0] == 0xA0 {
+            serial = fields
+                .next()
+                .ok_or(anyhow::anyhow!("invalid certificate"))?;
+        }
+        let _algorithm = fields.next();
+        let issuer = fields
+            .next()
+            .ok_or(anyhow::anyhow!("invalid certificate"))?;
+
+        let sha256 = Der::sequence(&[&Der::oid(OID_SHA256), &Der::null()]);
+        let rsa = Der::sequence(&[&Der::oid(OID_RSA_ENCRYPTION), &Der::null()]);
+
+        let signer_info = Der::sequence(&[
+            &Der::integer(1),
+            &Der::sequence(&[issuer, serial]),
+            &sha256,
+            &rsa,
+            &Der::tlv(0x04, signature),
+        ]);
+
+        let signed_data = Der::sequence(&[
+            &Der::integer(1),
+            &Der::tlv(0x31, &sha256),
+            &Der::sequence(&[&Der::oid(OID_DATA)]),
+            &Der::tlv(0xA0, &self.certificate),
+            &Der::tlv(0x31, &signer_info),
+        ]);
+
+        Ok(Der::sequence(&[
+            &Der::oid(OID_SIGNED_DATA),
+            &Der::tlv(0xA0, &signed_data),
+        ]))
+    }
+
+    /// Insert a v2 APK Signing Block right before the central directory of `apk`
+    pub fn v2(&self, apk: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let eocd = find_eocd(apk)?;
+        let cd_offset = u32::from_le_bytes(apk[eocd + 16..eocd + 20].try_into()?) as usize;
+        if cd_offset > eocd {
+            return Err(anyhow::anyhow!("invalid zip central directory offset"));
+        }
+
+        // The EOCD is digested as if the central directory started at the signing block
+        let digest = content_digest(&[&apk[..cd_offset], &apk[cd_offset..eocd], &apk[eocd..]]);
+
+        let public_key = Der::children(&self.certificate)?
+            .into_iter()
+            .next()
+            .map(Der::children)
+            .transpose()?
+            .and_then(|fields| {
+                // The subject public key info is the first field after the subject
+                let start = if fields.first()?[0] == 0xA0 { 1 } else { 0 };
+                fields.get(start + 5).map(|v| v.to_vec())
+            })
+            .ok_or(anyhow::anyhow!("certificate has no public key"))?;
+
+        let digests = prefixed(&[
+            &SIGNATURE_RSA_PKCS1_V1_5_WITH_SHA256.to_le_bytes(),
+            &prefixed(&[&digest]),
+        ]);
+        let signed_data = [
+            prefixed(&[&digests]),
+            prefixed(&[&prefixed(&[&self.certificate])]),
+            prefixed(&[]),
+        ]
+        .concat();
+
+        let signature = prefixed(&[
+            &SIGNATURE_RSA_PKCS1_V1_5_WITH_SHA256.to_le_bytes(),
+            &prefixed(&[&self.sign(&signed_data)]),
+        ]);
+        let signer = [
+            prefixed(&[&signed_data]),
+            prefixed(&[&signature]),
+            prefixed(&[&public_key]),
+        ]
+        .concat();
+        let value = prefixed(&[&prefixed(&[&signer])]);
+
+        let mut pair = Vec::new();
+        pair.extend(((value.len() + 4) as u64).to_le_bytes());
+        pair.extend(APK_SIGNATURE_SCHEME_V2_ID.to_le_bytes());
+        pair.extend(value);
+
+        let size = (pair.len() + 8 + APK_SIG_BLOCK_MAGIC.len()) as u64;
+        let mut block = Vec::new();
+        block.extend(size.to_le_bytes());
+        block.extend(pair);
+        block.extend(size.to_le_bytes());
+        block.extend(APK_SIG_BLOCK_MAGIC);
+
+        let mut signed = Vec::with_capacity(apk.len() + block.len());
+        signed.extend(&apk[..cd_offset]);
+        signed.extend(&block);
+        signed.extend(&apk[cd_offset..]);
+
+        let eocd = eocd + block.len();
+        signed[eocd + 16..eocd + 20]
+            .copy_from_slice(&((cd_offset + block.len()) as u32).to_le_bytes());
+        Ok(signed)
+    }
+}
+
+/// Whether the entry is part of a v1 signature and must be replaced when signing
+pub fn is_signature_file(name: &str) -> bool {
+    match name.strip_prefix("META-INF/") {
+        Some(file) if !file.contains('/') => {
+            file == "MANIFEST.MF"
+                || [".SF", ".RSA", ".DSA", ".EC"]
+                    .iter()
+                    .any(|ext| file.ends_with(ext))
+        }
+        _ => false,
+    }
+}
+
+fn digest(data: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(Sha256::digest(data))
+}
+
+/// `key: value` manifest line wrapped at 72 bytes
+fn header_line(key: &str, value: &str) -> String {
+    let line = format!("{key}: {value}");
+    let bytes = line.as_bytes();
+
+    let mut out = Vec::new();
+    let mut start = 0;
+    let mut width = 72;
+    while start < bytes.len() {
+        let mut end = (start + width).min(bytes.len());
+        // Never split a utf8 character between lines
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        if start > 0 {
+            out.push(b' ');
+        }
+        out.extend(&bytes[start..end]);
+        out.extend(b"\r\n");
+        start = end;
+        width = 71;
+    }
+
+    String::from_utf8(out).unwrap()
+}
+
+fn prefixed(parts: &[&[u8]]) -> Vec<u8> {
+    let len: usize = parts.iter().map(|part| part.len()).sum();
+    let mut data = Vec::with_capacity(len + 4);
+    data.extend((len as u32).to_le_bytes());
+    for part in parts {
+        data.extend(*part);
+    }
+    data
+}
+
+fn content_digest(sections: &[&[u8]]) -> Vec<u8> {
+    let mut chunks = Vec::new();
+    for section in sections {
+        for chunk in section.chunks(CHUNK_SIZE) {
+            let mut hasher = Sha256::new();
+            hasher.update([0xa5]);
+            hasher.update((chunk.len() as u32).to_le_bytes());
+            hasher.update(chunk);
+            chunks.push(hasher.finalize());
+        }
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update([0x5a]);
+    hasher.update((chunks.len() as u32).to_le_bytes());
+    for chunk in chunks {
+        hasher.update(chunk);
+    }
+    hasher.finalize().to_vec()
+}
+
+fn find_eocd(apk: &[u8]) -> anyhow::Result<usize> {
+    // The EOCD is 22 bytes followed by a comment of up to u16::MAX bytes
+    let min = apk.len().saturating_sub(22 + u16::MAX as usize);
+    (min..=apk.len().saturating_sub(22))
+        .rev()
+        .find(|&at| {
+            u32::from_le_bytes(apk[at..at + 4].try_into().unwrap()) == EOCD_SIGNATURE
+                && at + 22 + u16::from_le_bytes([apk[at + 20], apk[at + 21]]) as usize == apk.len()
+        })
+        .ok_or(anyhow::anyhow!("apk is not a valid zip archive"))
+}
+
+/// Tiny DER writer and reader for the few structures needed to sign an apk
+struct Der;
+
+impl Der {
+    fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut data = vec![tag];
+        let len = content.len();
+        if len < 0x80 {
+            data.push(len as u8);
+        } else {
+            let bytes = len.to_be_bytes();
+            let skip = bytes.iter().take_while(|b| **b == 0).count();
+            data.push(0x80 | (bytes.len() - skip) as u8);
+            data.extend(&bytes[skip..]);
+        }
+        data.extend(content);
+        data
+    }
+
+    fn sequence(items: &[&[u8]]) -> Vec<u8> {
+        Self::tlv(0x30, &items.concat())
+    }
+
+    fn oid(oid: &[u8]) -> Vec<u8> {
+        Self::tlv(0x06, oid)
+    }
+
+    fn null() -> Vec<u8> {
+        vec![0x05, 0x00]
+    }
+
+    fn integer(value: u8) -> Vec<u8> {
+        Self::tlv(0x02, &[value])
+    }
+
+    /// Header length and content length of the element at the start of `data`
+    fn header(data: &[u8]) -> anyhow::Result<(usize, usize)> {
+        let invalid = || anyhow::anyhow!("invalid DER encoding");
+        let first = *data.get(1).ok_or_else(invalid)? as usize;
+        if first < 0x80 {
+            return Ok((2, first));
+        }
+
+        let count = first & 0x7F;
+        let bytes = data.get(2..2 + count).ok_or_else(invalid)?;
+        let len = bytes.iter().fold(0usize, |len, b| (len << 8) | *b as usize);
+        Ok((2 + count, len))
+    }
+
+    /// Each complete child element of the constructed element at the start of `data`
+    fn children(data: &[u8]) -> anyhow::Result<Vec<&[u8]>> {
+        let (header, len) = Self::header(data)?;
+        let mut content = data
+            .get(header..header + len)
+            .ok_or(anyhow::anyhow!("invalid DER encoding"))?;
+
+        let mut children = Vec::new();
+        while !content.is_empty() {
+            let (header, len) = Self::header(content)?;
+            let (child, rest) = content.split_at((header + len).min(content.len()));
+            children.push(child);
+            content = rest;
+        }
+        Ok(children)
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use backhand::{FilesystemCompressor, FilesystemReader, FilesystemWriter, InnerNode, NodeHeader};
+
+/// Magic bytes at the start of a squashfs image
+const SQUASHFS_MAGIC: &[u8; 4] = b"hsqs";
+
+/// An AppImage split into it's ELF runtime and the squashfs payload appended to it
+///
+/// The runtime is reused as is when repacking so the output behaves exactly like the
+/// AppImage that was installed for the framework.
+pub struct AppImage {
+    path: PathBuf,
+    offset: u64,
+}
+
+impl AppImage {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)
+            .map_err(|e| anyhow::anyhow!("failed to open {}: {e}", path.display()))?;
+
+        let offset = payload_offset(&mut file)?;
+
+        let mut magic = [0u8; 4];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut magic)
+            .map_err(|_| anyhow::anyhow!("{} has no squashfs payload", path.display()))?;
+        if &magic != SQUASHFS_MAGIC {
+            return Err(anyhow::anyhow!(
+                "{} has no squashfs payload at offset {offset}",
+                path.display()
+            ));
+        }
+
+        Ok(Self { path, offset })
+    }
+
+    /// Bytes of the ELF runtime that mounts and runs the payload
+    pub fn runtime(&self) -> anyhow::Result<Vec<u8>> {
+        let mut runtime = vec![0u8; self.offset as usize];
+        File::open(&self.path)?.read_exact(&mut runtime)?;
+        Ok(runtime)
+    }
+
+    fn filesystem(&self) -> anyhow::Result<FilesystemReader<'static>> {
+        let reader = BufReader::new(File::open(&self.path)?);
+        Ok(FilesystemReader::from_reader_with_offset(
+            reader,
+            self.offset,
+        )?)
+    }
+
+    /// Extract the squashfs payload into `dest`, keeping directories, symlinks and file modes
+    pub fn extract(&self, dest: &Path) -> anyhow::Result<()> {
+        let filesystem = self.filesystem()?;
+        let mut links = Vec::new();
+
+        for node in filesystem.files() {
+            let path = dest.join(node.fullpath.strip_prefix("/").unwrap_or(&node.fullpath));
+
+            match &node.inner {
+                InnerNode::Dir(_) => std::fs::create_dir_all(&path)?,
+                InnerNode::File(file) => {
+                    if let Some(parent) = path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    let mut out = File::create(&path)?;
+                    std::io::copy(&mut filesystem.file(file).reader(), &mut out)?;
+                }
+                InnerNode::Symlink(link) => links.push((path.clone(), link.link.clone())),
+                // Devices, pipes, and sockets have no meaning inside of an AppImage
+                _ => continue,
+            }
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+
+                let mode = match node.inner {
+                    InnerNode::Symlink(_) => continue,
+                    // Directories must stay writable so their contents can be extracted
+                    InnerNode::Dir(_) => node.header.permissions as u32 | 0o700,
+                    _ => node.header.permissions as u32,
+                };
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode))?;
+            }
+        }
+
+        for (path, link) in links {
+            #[cfg(unix)]
+            {
+                std::os::unix::fs::symlink(&link, &path)?;
+            }
+            // Symlinks require elevated permissions on other platforms so the
+            // linked file is copied in it's place
+            #[cfg(not(unix))]
+            {
+                let source = path.parent().unwrap_or(dest).join(&link);
+                if source.is_file() {
+                    std::fs::copy(&source, &path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pack the directory `src` into a new AppImage at `out` using this AppImage's runtime
+    /// and squashfs compression
+    pub fn pack(&self, src: &Path, out: &Path) -> anyhow::Result<()> {
+        let original = self.filesystem()?;
+
+        let mut filesystem = FilesystemWriter::default();
+        filesystem.set_block_size(original.block_size);
+        filesystem.set_compressor(FilesystemCompressor::new(
+            original
+                .compressor
+                .ok_or(anyhow::anyhow!("AppImage payload has no compressor"))?
+                .into(),
+            None,
+        )?);
+        filesystem.set_root_mode(0o755);
+        add_dir(&mut filesystem, src, src)?;
+
+        let runtime = self.runtime()?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .write(true)
+            .open(out)?;
+        file.write_all(&runtime)?;
+        filesystem.write_with_offset(&mut file, runtime.len() as u64)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(out, std::fs::Permissions::from_mode(0o755))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Find the framework's binary inside of an extracted AppImage
+pub fn find_binary(root: &Path, name: &str) -> Option<PathBuf> {
+    [
+        root.join("bin").join(name),
+        root.join("usr").join("bin").join(name),
+        root.join(name),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+/// Directories of the libraries bundled inside of an extracted AppImage
+pub fn libraries(root: &Path) -> Vec<PathBuf> {
+    [
+        root.join("lib"),
+        root.join("usr").join("lib"),
+        root.join("lib").join("x86_64-linux-gnu"),
+        root.join("usr").join("lib").join("x86_64-linux-gnu"),
+    ]
+    .into_iter()
+    .filter(|path| path.is_dir())
+    .collect()
+}
+
+/// Header used for every node so the image does not leak the builder's uid/gid
+fn header(mode: u32) -> NodeHeader {
+    NodeHeader::new((mode & 0o7777) as u16, 0, 0, 0)
+}
+
+fn mode(metadata: &std::fs::Metadata) -> u32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        0o755
+    }
+}
+
+fn add_dir(filesystem: &mut FilesystemWriter, prefix: &Path, dir: &Path) -> anyhow::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?.flatten().collect::<Vec<_>>();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let name = path.strip_prefix(prefix).unwrap();
+        let metadata = std::fs::symlink_metadata(&path)?;
+
+        if metadata.is_symlink() {
+            filesystem.push_symlink(std::fs::read_link(&path)?, name, header(0o777))?;
+        } else if metadata.is_dir() {
+            filesystem.push_dir(name, header(mode(&metadata)))?;
+            add_dir(filesystem, prefix, &path)?;
+        } else {
+            filesystem.push_file_from_path(&path, name, header(mode(&metadata)))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Offset of the squashfs payload which is right after the end of the ELF runtime
+///
+/// The end of the runtime is the end of the section header table which is always the
+/// last part of the ELF file.
+fn payload_offset(file: &mut File) -> anyhow::Result<u64> {
+    let mut ident = [0u8; 64];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut ident)
+        .map_err(|_| anyhow::anyhow!("file is too small to be an AppImage"))?;
+
+    if &ident[..4] != b"\x7fELF" {
+        return Err(anyhow::anyhow!("AppImage runtime is not an ELF executable"));
+    }
+
+    let little = match ident[5] {
+        1 => true,
+        2 => false,
+        other => return Err(anyhow::anyhow!("invalid ELF data encoding: {other}")),
+    };
+
+    let u16_at = |at: usize| {
+        let bytes = [ident[at], ident[at + 1]];
+        if little {
+            u16::from_le_bytes(bytes) as u64
+        } else {
+            u16::from_be_bytes(bytes) as u64
+        }
+    };
+    let u32_at = |at: usize| {
+        let bytes = ident[at..at + 4].try_into().unwrap();
+        if little {
+            u32::from_le_bytes(bytes) as u64
+        } else {
+            u32::from_be_bytes(bytes) as u64
+        }
+    };
+    let u64_at = |at: usize| {
+        let bytes = ident[at..at + 8].try_into().unwrap();
+        if little {
+            u64::from_le_bytes(bytes)
+        } else {
+            u64::from_be_bytes(bytes)
+        }
+    };
+
+    // (e_shoff, e_shentsize, e_shnum)
+    let (shoff, shentsize, shnum) = match ident[4] {
+        1 => (u32_at(0x20), u16_at(0x2E), u16_at(0x30)),
+        2 => (u64_at(0x28), u16_at(0x3A), u16_at(0x3C)),
+        other => return Err(anyhow::anyhow!("invalid ELF class: {other}")),
+    };
+
+    Ok(shoff + shentsize * shnum)
+}
@@ -0,0 +1,152 @@
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::{
+    appimage::{self, AppImage},
+    macos::AppBundle,
+};
+use crate::config::{Framework, Target};
+
+/// Executable of the framework and how it has to be started on this system
+#[derive(Debug, Clone)]
+pub struct Executable {
+    pub path: PathBuf,
+    /// Options passed before anything else, love only reads it's own before the game's path
+    pub args: Vec<OsString>,
+    /// Directories of the libraries it loads, added to `LD_LIBRARY_PATH`
+    pub libraries: Vec<PathBuf>,
+}
+
+impl Executable {
+    pub(super) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            args: Vec::new(),
+            libraries: Vec::new(),
+        }
+    }
+
+    pub fn command(&self) -> tokio::process::Command {
+        let mut command = tokio::process::Command::new(&self.path);
+        command.args(&self.args);
+
+        if !self.libraries.is_empty() {
+            let inherited = std::env::var_os("LD_LIBRARY_PATH").unwrap_or_default();
+            let paths = self
+                .libraries
+                .iter()
+                .cloned()
+                .chain(std::env::split_paths(&inherited))
+                // An empty entry would search the working directory
+                .filter(|path| !path.as_os_str().is_empty());
+            if let Ok(paths) = std::env::join_paths(paths) {
+                command.env("LD_LIBRARY_PATH", paths);
+            }
+        }
+        command
+    }
+}
+
+/// Find the framework's executable for the target in `dir`, an installed version or local build
+///
+/// Build trees have the framework's binary at their root, which is used first. Otherwise the
+/// AppImage or app bundle is extracted next to itself, and again whenever it's replaced, so it
+/// runs without FUSE or a copy of the bundle. On windows the console build, `lovec.exe`, is
+/// used for `console` when there is one.
+pub fn find(
+    dir: &Path,
+    framework: Framework,
+    target: Target,
+    console: bool,
+) -> anyhow::Result<Executable> {
+    let name = framework.to_string();
+    let root = dir.join(&name);
+
+    let executable = match target {
+        Target::Win64 => windows(dir, &name, framework, console),
+        Target::Linux if root.is_file() => Executable::new(root),
+        Target::Linux => {
+            let appimage = dir.join(format!("{name}.AppImage"));
+            let appdir = dir.join(format!("{name}.AppDir"));
+            if is_outdated(&appdir, &appimage) {
+                extract(&appdir, |dest| AppImage::open(&appimage)?.extract(dest))?;
+            }
+
+            let binary = appimage::find_binary(&appdir, &name).ok_or(anyhow::anyhow!(
+                "{name} binary not found in {}",
+                appimage.display()
+            ))?;
+            let mut executable = Executable::new(binary);
+            executable.libraries = appimage::libraries(&appdir);
+            executable
+        }
+        Target::Macos if root.is_file() => Executable::new(root),
+        Target::Macos => {
+            let bundle = dir.join(format!("{name}.app.zip"));
+            let app = dir.join(format!("{name}.app"));
+            if bundle.is_file() && is_outdated(&app, &bundle) {
+                extract(&app, |dest| {
+                    AppBundle::open(&bundle)?.extract(dest).map(|_| ())
+                })?;
+            }
+            Executable::new(app.join("Contents").join("MacOS").join(&name))
+        }
+        _ => return Err(anyhow::anyhow!("{framework} can't be run for {target}")),
+    };
+
+    if !executable.path.is_file() {
+        return Err(anyhow::anyhow!(
+            "{framework} executable {} not found",
+            executable.path.display()
+        ));
+    }
+    Ok(executable)
+}
+
+/// Windows executable `<name>.exe` in `dir`, or it's console build `<name>c.exe` for `console`
+///
+/// Without a console build love opens it's console with `--console`, which lovr doesn't have.
+pub(super) fn windows(dir: &Path, name: &str, framework: Framework, console: bool) -> Executable {
+    let console_build = dir.join(format!("{name}c.exe"));
+    if console && console_build.is_file() {
+        return Executable::new(console_build);
+    }
+
+    let mut executable = Executable::new(dir.join(format!("{name}.exe")));
+    if console && framework == Framework::Love {
+        executable.args.push("--console".into());
+    }
+    executable
+}
+
+/// Whether `extracted` is missing or older than the `archive` it was extracted from
+fn is_outdated(extracted: &Path, archive: &Path) -> bool {
+    let modified = |path: &Path| {
+        std::fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    };
+    !extracted.is_dir() || modified(extracted) < modified(archive)
+}
+
+/// Extract into `dest` through a temporary directory, so an interrupted extraction isn't used
+fn extract(dest: &Path, extract: impl FnOnce(&Path) -> anyhow::Result<()>) -> anyhow::Result<()> {
+    let mut partial = dest.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    if partial.exists() {
+        std::fs::remove_dir_all(&partial)?;
+    }
+    std::fs::create_dir_all(&partial)?;
+    extract(&partial)?;
+
+    if dest.exists() {
+        std::fs::remove_dir_all(dest)?;
+    }
+    std::fs::rename(&partial, dest)?;
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::{Framework, Target};
+
+/// Files a local build of the framework needs for the target, relative to it's directory
+///
+/// A local build is laid out like an installed version, so windows needs the executable and
+/// the libraries it loads next to it.
+fn required(framework: Framework, target: Target) -> anyhow::Result<Vec<PathBuf>> {
+    Ok(match target {
+        Target::Win64 => {
+            let mut files = vec![PathBuf::from(format!("{framework}.exe"))];
+            if framework == Framework::Love {
+                files.extend(["love.dll", "lua51.dll"].map(PathBuf::from));
+            }
+            files
+        }
+        Target::Linux => vec![PathBuf::from(format!("{framework}.AppImage"))],
+        Target::Macos => vec![PathBuf::from(format!("{framework}.app.zip"))],
+        Target::Android => vec![PathBuf::from(format!("{framework}.apk"))],
+        Target::Web => vec![Path::new("compat").join("love.wasm")],
+        Target::Ios => {
+            return Err(anyhow::anyhow!(
+                "local {framework} builds can't be used for {target}"
+            ))
+        }
+    })
+}
+
+/// Whether `dir` is a build tree, with the framework's binary at it's root
+///
+/// love's binary loads `liblove` from next to it, so the library has to be there as well.
+fn is_build_tree(dir: &Path, framework: Framework, library: &str) -> bool {
+    dir.join(framework.to_string()).is_file()
+        && (framework != Framework::Love
+            || std::fs::read_dir(dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .any(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    name.starts_with("liblove") && name.contains(library)
+                }))
+}
+
+/// Check that the local build at `dir` has everything the target is built from
+///
+/// When the framework is only `run`, build trees with the framework's binary and libraries at
+/// their root are accepted in place of the AppImage on linux and the app bundle on macos.
+/// Games are fused into those, so building needs them either way.
+pub fn verify(dir: &Path, framework: Framework, target: Target, run: bool) -> anyhow::Result<()> {
+    if !dir.is_dir() {
+        return Err(anyhow::anyhow!(
+            "local {framework} build {} is not a directory",
+            dir.display()
+        ));
+    }
+
+    let alternative = match target {
+        Target::Linux if run && is_build_tree(dir, framework, ".so") => return Ok(()),
+        Target::Macos
+            if run
+                && (is_build_tree(dir, framework, ".dylib")
+                    || dir.join(format!("{framework}.app")).is_dir()) =>
+        {
+            return Ok(())
+        }
+        Target::Linux | Target::Macos if !run => {
+            ", build trees without it can only be used by `lbt run`"
+        }
+        Target::Linux | Target::Macos => match framework {
+            Framework::Love => ", or a love binary with liblove next to it",
+            Framework::Lovr => ", or a lovr binary",
+        },
+        _ => "",
+    };
+
+    let missing = required(framework, target)?
+        .into_iter()
+        .filter(|file| !dir.join(file).is_file())
+        .map(|file| file.display().to_string())
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(anyhow::anyhow!(
+            "local {framework} build {} is missing {} for {target}{alternative}",
+            dir.display(),
+            missing.join(", ")
+        ));
+    }
+    Ok(())
+}
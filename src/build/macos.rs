@@ -0,0 +1,162 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use crate::extract;
+
+/// Zipped `.app` bundle of a framework
+///
+/// Entries are copied without being decompressed so symlinks and unix modes inside of
+/// the bundle's frameworks are kept, even when building on a system that doesn't support them.
+pub struct AppBundle {
+    path: PathBuf,
+    archive: ZipArchive<BufReader<File>>,
+    /// Top level `<framework>.app/` directory inside of the zip
+    prefix: String,
+}
+
+impl AppBundle {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let archive =
+            ZipArchive::new(BufReader::new(File::open(path).map_err(|e| {
+                anyhow::anyhow!("failed to open {}: {e}", path.display())
+            })?))?;
+
+        let prefix = archive
+            .file_names()
+            .filter(|name| !name.starts_with("__MACOSX"))
+            .find_map(|name| {
+                name.split_once(".app/")
+                    .map(|(app, _)| format!("{app}.app/"))
+            })
+            .ok_or(anyhow::anyhow!(
+                "no .app bundle found in {}",
+                path.display()
+            ))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            archive,
+            prefix,
+        })
+    }
+
+    /// Parsed `Contents/Info.plist` of the bundle
+    pub fn info(&mut self) -> anyhow::Result<plist::Dictionary> {
+        let mut file = self
+            .archive
+            .by_name(&format!("{}Contents/Info.plist", self.prefix))?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+
+        plist::from_bytes(&content).map_err(|e| anyhow::anyhow!("invalid Info.plist: {e}"))
+    }
+
+    /// Extract the bundle as the `.app` directory `app`, returning the path of it's executable
+    ///
+    /// Symlinks and unix modes are restored so the bundle's frameworks can be loaded, see
+    /// [`extract::unzip_dir`].
+    pub fn extract(&mut self, app: &Path) -> anyhow::Result<PathBuf> {
+        let executable = self
+            .info()?
+            .get("CFBundleExecutable")
+            .and_then(|value| value.as_string())
+            .ok_or(anyhow::anyhow!("Info.plist has no CFBundleExecutable"))?
+            .to_string();
+
+        extract::unzip_dir(&self.path, &self.prefix, app)?;
+        Ok(app.join("Contents").join("MacOS").join(executable))
+    }
+
+    /// Write the bundle to a new zip as `<name>.app`
+    ///
+    /// `info` replaces `Contents/Info.plist` and each resource is written to, or replaces the
+    /// file at, `Contents/Resources/<resource>`.
+    pub fn write(
+        &mut self,
+        out: &Path,
+        name: &str,
+        info: &plist::Dictionary,
+        resources: &[(String, Vec<u8>)],
+    ) -> anyhow::Result<()> {
+        let app = format!("{name}.app/");
+        let options = SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .unix_permissions(0o644);
+
+        let mut writer = ZipWriter::new(File::create(out)?);
+
+        for i in 0..self.archive.len() {
+            let file = self.archive.by_index_raw(i)?;
+            let Some(path) = file.name().strip_prefix(&self.prefix).map(str::to_string) else {
+                continue;
+            };
+
+            if path == "Contents/Info.plist"
+                || resources
+                    .iter()
+                    .any(|(resource, _)| path == format!("Contents/Resources/{resource}"))
+            {
+                continue;
+            }
+
+            // Raw copies are always written as regular files so directories and symlinks are
+            // recreated with their original modes
+            let mode = file.unix_mode().unwrap_or(0o644);
+            let entry = SimpleFileOptions::default().unix_permissions(mode & 0o777);
+            if file.is_dir() {
+                writer.add_directory(format!("{app}{path}"), entry)?;
+            } else if mode & 0o170000 == 0o120000 {
+                drop(file);
+                let mut link = String::new();
+                self.archive.by_index(i)?.read_to_string(&mut link)?;
+                writer.add_symlink(format!("{app}{path}"), link, entry)?;
+            } else {
+                writer.raw_copy_file_rename(file, format!("{app}{path}"))?;
+            }
+        }
+
+        let mut plist = Vec::new();
+        plist::to_writer_xml(&mut plist, info)?;
+        writer.start_file(format!("{app}Contents/Info.plist"), options)?;
+        writer.write_all(&plist)?;
+
+        for (resource, content) in resources {
+            writer.start_file(format!("{app}Contents/Resources/{resource}"), options)?;
+            writer.write_all(content)?;
+        }
+
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Convert an icon to the `icns` format used by macos
+///
+/// `icns` files are used as is while `png` files are converted. The png must be square
+/// with a size of 16, 32, 64, 128, 256, 512, or 1024 pixels.
+pub fn icns(icon: &Path) -> anyhow::Result<Vec<u8>> {
+    match icon.extension().and_then(|v| v.to_str()) {
+        Some("icns") => Ok(std::fs::read(icon)?),
+        Some("png") => {
+            let image = icns::Image::read_png(BufReader::new(File::open(icon)?))?;
+            let mut family = icns::IconFamily::new();
+            family.add_icon(&image).map_err(|e| {
+                anyhow::anyhow!("failed to convert {} to icns: {e}", icon.display())
+            })?;
+
+            let mut content = Vec::new();
+            family.write(&mut content)?;
+            Ok(content)
+        }
+        _ => Err(anyhow::anyhow!(
+            "unsupported macos icon {}; expected a png or icns file",
+            icon.display()
+        )),
+    }
+}
@@ -4,10 +4,26 @@ use std::path::{Path, PathBuf};
 use zip::write::SimpleFileOptions;
 
 use crate::{
-    config::{Build, Config, Framework, Target},
-    git::Client,
+    config::{Build, Canvas, Config, Framework, Target},
+    git::{Client, Download},
+    index::{Index, Install},
+    lock::{Lock, Locked},
 };
-use crate::{Progress, SpinnerError};
+use crate::{Progress, SpinnerError, Version, DATA};
+
+mod android;
+mod appimage;
+mod executable;
+mod local;
+mod macos;
+mod nightly;
+mod web;
+
+pub use executable::Executable;
+
+use android::{Apk, Customizations, Signer};
+use appimage::AppImage;
+use macos::AppBundle;
 
 //      Ensure framework is installed for the specific version and target
 //      Copy needed files to build directory
@@ -25,18 +41,54 @@ pub struct Builder<'conf> {
     config: &'conf Config,
     framework: &'conf Framework,
     build: &'conf Build,
+    /// Version of the framework the build's requirement was resolved to
+    version: Version,
+    /// Fail instead of adding versions that aren't locked to `lbt.lock`
+    locked: bool,
+    /// Add versions that aren't locked yet to `lbt.lock`
+    update_lock: bool,
+    /// Only run the framework instead of building with it, see [`local::verify`]
+    run: bool,
 }
 
 impl<'conf> Builder<'conf> {
-    pub fn new(framework: &'conf Framework, build: &'conf Build, config: &'conf Config) -> Self {
+    /// Builder for a version of the framework, see [`resolve`] for resolving the build's one
+    pub fn new(
+        framework: &'conf Framework,
+        build: &'conf Build,
+        version: Version,
+        config: &'conf Config,
+    ) -> Self {
         Self {
             root: std::env::current_dir().unwrap(),
             framework,
             build,
+            version,
             config,
+            locked: false,
+            update_lock: true,
+            run: false,
         }
     }
 
+    /// Require every version to already be locked in `lbt.lock`, e.g. in CI
+    pub fn with_locked(mut self, locked: bool) -> Self {
+        self.locked = locked;
+        self
+    }
+
+    /// Only run the framework, which local build trees are enough for
+    pub fn with_run(mut self, run: bool) -> Self {
+        self.run = run;
+        self
+    }
+
+    /// Leave `lbt.lock` as it is, e.g. when a version is only used once
+    pub fn with_update_lock(mut self, update_lock: bool) -> Self {
+        self.update_lock = update_lock;
+        self
+    }
+
     pub async fn bundle(&self, client: &Client) -> anyhow::Result<()> {
         let targets = if self.build.targets.is_empty() {
             &[Target::default()]
@@ -44,110 +96,215 @@ impl<'conf> Builder<'conf> {
             self.build.targets.as_slice()
         };
 
+        // Failures are reported by the spinner, the remaining targets are still built
+        let mut failed = 0;
         for target in targets {
-            let mut spinner = Progress::new(format!("[{target}]"));
-            let tag = format!("[{}:{target}]", self.framework);
-            let mut fail = false;
-
-            spinner.update(format!("{tag} installing {}", self.framework));
-            if self
-                .ensure_framework_installed(client, &mut spinner)
-                .await
-                .ok_or_spin(
-                    &mut spinner,
-                    format!("[{target}] failed to install {}", self.framework),
-                )
-                .is_none()
-            {
-                fail = true;
+            if self.bundle_target(client, *target).await.is_err() {
+                failed += 1;
             }
+        }
 
-            spinner.update(format!("{tag} creating output directory"));
-            let target_dir = match self.output_dir(*target).ok_or_spin(
+        if failed > 0 {
+            return Err(anyhow::anyhow!(
+                "{failed} of {} {} builds failed",
+                targets.len(),
+                self.framework
+            ));
+        }
+        Ok(())
+    }
+
+    /// Build the project for a single target, returning the output directory
+    pub async fn bundle_target(&self, client: &Client, target: Target) -> anyhow::Result<PathBuf> {
+        let mut spinner = Progress::new(format!("[{target}]"));
+        let tag = format!("[{}:{target}]", self.framework);
+        let mut fail = false;
+
+        spinner.update(format!("{tag} installing {}", self.framework));
+        if self
+            .ensure_framework_installed(client, target, &mut spinner)
+            .await
+            .ok_or_spin(
                 &mut spinner,
-                format!("[{target}] failed to create output directory"),
-            ) {
-                Some(td) => td,
-                None => continue,
-            };
-
-            spinner.update(format!("{tag} copying dynamic libraries"));
-            if self
-                .copy_files(*target, &target_dir)
-                .ok_or_spin(
-                    &mut spinner,
-                    format!("{tag} failed to copy dynamic libraries"),
-                )
-                .is_none()
-            {
-                fail = true;
-            }
+                format!("[{target}] failed to install {}", self.framework),
+            )
+            .is_none()
+        {
+            // Nothing can be built without the framework
+            spinner.finish_fail(format!("{tag} Build failed").as_str());
+            return Err(anyhow::anyhow!("{tag} Build failed"));
+        }
 
-            spinner.update(format!("{tag} compressing source and building executable"));
-            if self
-                .build_executable(*target, &target_dir)
-                .ok_or_spin(&mut spinner, format!("{tag} failed to build executable"))
-                .is_none()
-            {
-                fail = true;
-            }
+        spinner.update(format!("{tag} creating output directory"));
+        let target_dir = match self.output_dir(target).ok_or_spin(
+            &mut spinner,
+            format!("[{target}] failed to create output directory"),
+        ) {
+            Some(td) => td,
+            None => return Err(anyhow::anyhow!("{tag} failed to create output directory")),
+        };
 
-            spinner.update(format!("{tag} packaging the executable and it's libraries"));
-            if self
-                .package(*target, &target_dir)
-                .ok_or_spin(&mut spinner, format!("{tag} failed to package final build"))
-                .is_none()
-            {
-                fail = true
-            }
+        spinner.update(format!("{tag} copying dynamic libraries"));
+        if self
+            .copy_files(target, &target_dir)
+            .ok_or_spin(
+                &mut spinner,
+                format!("{tag} failed to copy dynamic libraries"),
+            )
+            .is_none()
+        {
+            fail = true;
+        }
 
-            if fail {
-                spinner.finish_fail(format!("{tag} Build failed").as_str());
-            } else {
-                spinner.finish_success(format!("{tag} Build finished").as_str());
-            }
+        spinner.update(format!("{tag} compressing source and building executable"));
+        if self
+            .build_executable(target, &target_dir)
+            .ok_or_spin(&mut spinner, format!("{tag} failed to build executable"))
+            .is_none()
+        {
+            fail = true;
         }
 
-        Ok(())
+        spinner.update(format!("{tag} packaging the executable and it's libraries"));
+        if self
+            .package(target, &target_dir)
+            .ok_or_spin(&mut spinner, format!("{tag} failed to package final build"))
+            .is_none()
+        {
+            fail = true
+        }
+
+        if fail {
+            spinner.finish_fail(format!("{tag} Build failed").as_str());
+            return Err(anyhow::anyhow!("{tag} Build failed"));
+        }
+
+        spinner.finish_success(format!("{tag} Build finished").as_str());
+        Ok(target_dir)
     }
 
+    /// Install the framework for the target as it is locked in `lbt.lock`
+    ///
+    /// Versions that aren't locked yet are resolved and added to the lock, unless the lock is
+    /// required to be up to date or isn't updated.
     pub async fn ensure_framework_installed(
         &self,
         client: &Client,
+        target: Target,
         spinner: &mut Progress,
     ) -> anyhow::Result<()> {
-        // PERF: Caching / Auth / Parse from html
-        let releases = client
-            .releases(self.framework.owner(), self.framework.repo())
-            .await?;
+        let framework = *self.framework;
+        let version = &self.version;
+
+        // Local builds aren't installed or locked
+        if self.build.path.is_some() {
+            return local::verify(&self.framework_dir(target), framework, target, self.run);
+        }
 
-        if self.build.version < self.framework.min_version() {
+        let mut lock = Lock::parse(&self.root)?;
+        let locked = lock
+            .get(framework, target)
+            .filter(|locked| locked.version == *version);
+        if self.locked && locked.is_none() {
             return Err(anyhow::anyhow!(
-                "minimum supported love version is {}",
-                self.framework.min_version()
+                "lbt.lock doesn't lock {framework} {version} for {target}, run `lbt update`"
             ));
         }
 
-        let release = match releases.iter().find(|r| r.tag == self.build.version) {
-            Some(release) => release,
-            None => {
+        install(client, framework, version, target, locked, spinner).await?;
+        if locked.is_some() || !self.update_lock {
+            return Ok(());
+        }
+
+        if let Some(locked) = lock_install(client, framework, version, target, spinner).await? {
+            lock.insert(locked);
+            lock.save(&self.root)
+                .log_err_in_spin(spinner, "failed to update lbt.lock")?;
+        }
+        Ok(())
+    }
+
+    /// Directory the framework is used from for the target
+    ///
+    /// The build's local `path` is used for every target in place of the installed version
+    pub fn framework_dir(&self, target: Target) -> PathBuf {
+        match &self.build.path {
+            Some(path) => self.root.join(path),
+            None => self.framework.path(target, &self.version),
+        }
+    }
+
+    /// Executable of the framework for the target, see [`executable::find`]
+    pub fn executable(&self, target: Target, console: bool) -> anyhow::Result<Executable> {
+        executable::find(
+            &self.framework_dir(target),
+            *self.framework,
+            target,
+            console,
+        )
+    }
+
+    /// Executable of the game's fused build for the target, see [`Builder::artifact`]
+    ///
+    /// The fused game opens it's console like the framework it's built from, see
+    /// [`executable::find`]. Linux builds run from the directory the AppImage is packed from, and
+    /// the zipped app bundle of macos builds is extracted next to it first.
+    pub fn release_executable(&self, target: Target, console: bool) -> anyhow::Result<Executable> {
+        let artifact = self.artifact(target);
+        let executable = match target {
+            Target::Win64 => executable::windows(
+                &self.target_dir(target),
+                &self.config.project.name,
+                *self.framework,
+                console,
+            ),
+            // The AppImage needs FUSE, the directory it's packed from doesn't
+            Target::Linux => {
+                let appdir = self.appdir(&self.target_dir(target));
+                let binary = appimage::find_binary(&appdir, &self.framework.to_string()).ok_or(
+                    anyhow::anyhow!(
+                        "{} binary not found in {}",
+                        self.framework,
+                        appdir.display()
+                    ),
+                )?;
+                let mut executable = Executable::new(binary);
+                executable.libraries = appimage::libraries(&appdir);
+                executable
+            }
+            Target::Macos => Executable::new(
+                AppBundle::open(&artifact)?.extract(
+                    &self
+                        .target_dir(target)
+                        .join(format!("{}.app", self.config.project.name)),
+                )?,
+            ),
+            _ => {
                 return Err(anyhow::anyhow!(
-                    "release version {} for {} was not found",
-                    self.build.version,
-                    self.framework
+                    "{target} builds can't be run on this system"
                 ))
             }
         };
 
-        release.install(self.framework.to_string(), spinner).await
+        if !executable.path.is_file() {
+            return Err(anyhow::anyhow!(
+                "{} not found, the build failed to make it",
+                executable.path.display()
+            ));
+        }
+        Ok(executable)
     }
 
-    pub fn output_dir(&self, target: Target) -> anyhow::Result<PathBuf> {
-        let target_dir = self
-            .root
+    /// Directory the target is built in, `build/<framework>/<target>`
+    pub fn target_dir(&self, target: Target) -> PathBuf {
+        self.root
             .join("build")
             .join(self.framework.to_string())
-            .join(target.to_string());
+            .join(target.to_string())
+    }
+
+    pub fn output_dir(&self, target: Target) -> anyhow::Result<PathBuf> {
+        let target_dir = self.target_dir(target);
 
         if target_dir.exists() {
             std::fs::remove_dir_all(&target_dir)?;
@@ -157,8 +314,69 @@ impl<'conf> Builder<'conf> {
         Ok(target_dir)
     }
 
+    /// Final build of the game for the target, e.g. the fused `<name>.exe` on windows
+    pub fn artifact(&self, target: Target) -> PathBuf {
+        let name = &self.config.project.name;
+        self.target_dir(target).join(match target {
+            Target::Win64 => format!("{name}.exe"),
+            Target::Linux => format!("{name}.AppImage"),
+            // The zipped app bundle
+            Target::Macos | Target::Web => format!("{name}.zip"),
+            Target::Android => format!("{name}.apk"),
+            Target::Ios => format!("{name}.ipa"),
+        })
+    }
+
+    /// Whether the target's build is missing or older than the files it's built from
+    ///
+    /// The game's source, `lbt.toml`, `lbt.lock`, icons, and the framework's own files are
+    /// compared by their modification time.
+    pub fn is_stale(&self, target: Target) -> bool {
+        let Ok(built) = std::fs::metadata(self.artifact(target)).and_then(|meta| meta.modified())
+        else {
+            return true;
+        };
+
+        let mut files = vec![self.root.join("lbt.toml"), self.root.join("lbt.lock")];
+        files.extend(self.icon(target));
+        let mut pending = vec![self.root.join("src"), self.framework_dir(target)];
+        while let Some(dir) = pending.pop() {
+            for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+                match entry.file_type() {
+                    // Only the game's source is searched, the framework's files are at it's root
+                    Ok(kind) if kind.is_dir() && dir.starts_with(self.root.join("src")) => {
+                        pending.push(entry.path())
+                    }
+                    Ok(kind) if kind.is_file() => files.push(entry.path()),
+                    _ => {}
+                }
+            }
+        }
+
+        files.iter().any(|file| {
+            std::fs::metadata(file)
+                .and_then(|meta| meta.modified())
+                .is_ok_and(|modified| modified > built)
+        })
+    }
+
     pub fn copy_files(&self, target: Target, dest: &Path) -> anyhow::Result<()> {
-        for entry in std::fs::read_dir(self.framework.path(target))?.flatten() {
+        // Libraries are bundled inside of the AppImage, app bundle, and apk
+        if matches!(target, Target::Linux | Target::Macos | Target::Android) {
+            return Ok(());
+        }
+
+        if target == Target::Web {
+            let threads = self
+                .config
+                .target
+                .get(&target)
+                .and_then(|settings| settings.threads)
+                .unwrap_or(false);
+            return web::copy_runtime(&self.framework_dir(target), threads, dest);
+        }
+
+        for entry in std::fs::read_dir(self.framework_dir(target))?.flatten() {
             if let Some("dll") = entry.path().extension().and_then(|v| v.to_str()) {
                 std::fs::copy(entry.path(), dest.join(entry.path().file_name().unwrap()))?;
             }
@@ -167,22 +385,75 @@ impl<'conf> Builder<'conf> {
         Ok(())
     }
 
+    /// Icon for the target, falling back to the project icon
+    ///
+    /// Icons are resolved relative to the `assets` directory first and then the project root
+    pub fn icon(&self, target: Target) -> Option<PathBuf> {
+        let icon = self
+            .config
+            .target
+            .get(&target)
+            .and_then(|settings| settings.icon.as_ref())
+            .or(self.config.project.icon.as_ref())?;
+
+        [self.root.join("assets").join(icon), self.root.join(icon)]
+            .into_iter()
+            .find(|path| path.is_file())
+    }
+
+    /// AppImage the linux build is made from
+    fn appimage(&self) -> PathBuf {
+        self.framework_dir(Target::Linux)
+            .join(format!("{}.AppImage", self.framework))
+    }
+
+    /// Directory the fused AppImage contents are extracted to
+    fn appdir(&self, dest: &Path) -> PathBuf {
+        dest.join(&self.config.project.name)
+    }
+
     pub fn build_executable(&self, target: Target, dest: &Path) -> anyhow::Result<()> {
-        let exe = dest.join(format!("{}.exe", self.config.project.name));
         let compressed = format!("{}.{}", self.config.project.name, self.framework);
+
+        let mut archive = Archive::new(self.root.join("src"), dest.join(&compressed))?;
+        archive.add_dir(&self.root.join("src"), true)?;
+        archive.finish()?;
+
         // Build based on target
         match target {
             Target::Win64 => {
-                let mut archive = Archive::new(self.root.join("src"), dest.join(&compressed))?;
-                archive.add_dir(&self.root.join("src"), true)?;
-                archive.finish()?;
+                let name = &self.config.project.name;
+                let mut executables = vec![(
+                    self.executable(target, false)?.path,
+                    dest.join(format!("{name}.exe")),
+                )];
+                // The console build is fused as well, like love ships `lovec.exe` next to it
+                let console = self.executable(target, true)?;
+                if console.args.is_empty() && console.path != executables[0].0 {
+                    executables.push((console.path, dest.join(format!("{name}c.exe"))));
+                }
 
-                std::fs::copy(self.framework.exe(target), &exe)?;
+                for (framework, exe) in executables {
+                    std::fs::copy(framework, &exe)?;
+                    let mut out = std::fs::OpenOptions::new().append(true).open(&exe)?;
+                    out.write_all(&std::fs::read(dest.join(&compressed))?)?;
+                }
+            }
+            Target::Linux => {
+                let appdir = self.appdir(dest);
+                AppImage::open(self.appimage())?.extract(&appdir)?;
 
-                let mut out = std::fs::OpenOptions::new().append(true).open(&exe)?;
+                let binary = appimage::find_binary(&appdir, &self.framework.to_string()).ok_or(
+                    anyhow::anyhow!("{} binary not found in AppImage", self.framework),
+                )?;
+
+                let mut out = std::fs::OpenOptions::new().append(true).open(&binary)?;
                 out.write_all(&std::fs::read(dest.join(&compressed))?)?;
             }
-            _ => unimplemented!(),
+            Target::Macos => self.build_app(dest, &compressed)?,
+            Target::Android => self.build_apk(dest, &compressed)?,
+            Target::Web => self.build_web(dest, &compressed)?,
+            _ => return Err(anyhow::anyhow!("{target} builds are not supported")),
         }
 
         self.apply_customizations(target, dest)?;
@@ -190,18 +461,196 @@ impl<'conf> Builder<'conf> {
         Ok(())
     }
 
-    pub fn apply_customizations(&self, target: Target, _dest: &Path) -> anyhow::Result<()> {
-        // TODO: If custom icon then apply that to executable
+    /// Build the zipped `<name>.app` bundle with the game in `Contents/Resources`
+    ///
+    /// The icon and `Info.plist` are written while copying the bundle so there is nothing
+    /// left to customize or package afterwards.
+    fn build_app(&self, dest: &Path, compressed: &str) -> anyhow::Result<()> {
+        let project = &self.config.project;
+        let mut bundle = AppBundle::open(
+            self.framework_dir(Target::Macos)
+                .join(format!("{}.app.zip", self.framework)),
+        )?;
+
+        let mut info = bundle.info()?;
+        let version = project.version().to_string();
+        info.insert("CFBundleIdentifier".into(), project.identifier().into());
+        info.insert("CFBundleName".into(), project.name.clone().into());
+        info.insert("CFBundleDisplayName".into(), project.name.clone().into());
+        info.insert("CFBundleShortVersionString".into(), version.clone().into());
+        info.insert("CFBundleVersion".into(), version.into());
+        // Would claim `.love` files for the fused game
+        info.remove("UTExportedTypeDeclarations");
+        info.remove("CFBundleDocumentTypes");
+
+        let mut resources = vec![(
+            compressed.to_string(),
+            std::fs::read(dest.join(compressed))?,
+        )];
+
+        if let Some(icon) = self.icon(Target::Macos) {
+            resources.push((format!("{}.icns", project.name), macos::icns(&icon)?));
+            info.insert("CFBundleIconFile".into(), project.name.clone().into());
+            // Takes priority over `CFBundleIconFile` and points at the framework's icon
+            info.remove("CFBundleIconName");
+        }
+
+        bundle.write(
+            &dest.join(format!("{}.zip", project.name)),
+            &project.name,
+            &info,
+            &resources,
+        )
+    }
+
+    /// Build the signed `<name>.apk` with the game embedded in the framework's apk
+    fn build_apk(&self, dest: &Path, compressed: &str) -> anyhow::Result<()> {
+        if *self.framework != Framework::Love {
+            return Err(anyhow::anyhow!(
+                "android builds are only supported for love"
+            ));
+        }
+
+        let project = &self.config.project;
+        let settings = self.config.target.get(&Target::Android);
+
+        let keystore = settings
+            .and_then(|settings| settings.keystore.as_ref())
+            .ok_or(anyhow::anyhow!(
+                "android builds must be signed; add a keystore to [target.android] in lbt.toml"
+            ))?;
+        let password = std::env::var(&keystore.password_env).map_err(|_| {
+            anyhow::anyhow!(
+                "keystore password must be set with the {} environment variable",
+                keystore.password_env
+            )
+        })?;
+        let signer = Signer::from_pkcs12(
+            &std::fs::read(self.root.join(&keystore.path))?,
+            &password,
+            keystore.alias.as_deref(),
+        )?;
+
+        let version = project.version();
+        let version_code = settings
+            .and_then(|settings| settings.version_code)
+            .unwrap_or(
+                (version.major * 10000 + version.minor * 100 + version.patch.unwrap_or(0)) as u32,
+            );
+
+        let icon = match self.icon(Target::Android) {
+            Some(icon) if icon.extension().and_then(|v| v.to_str()) == Some("png") => {
+                Some(std::fs::read(icon)?)
+            }
+            Some(icon) => {
+                return Err(anyhow::anyhow!(
+                    "unsupported android icon {}; expected a png file",
+                    icon.display()
+                ))
+            }
+            None => None,
+        };
+
+        Apk::open(
+            self.framework_dir(Target::Android)
+                .join(format!("{}.apk", self.framework)),
+        )?
+        .write(
+            &dest.join(format!("{}.apk", project.name)),
+            &Customizations {
+                package: &project.identifier(),
+                label: &project.name,
+                version_code,
+                version_name: &version.to_string(),
+                game: &std::fs::read(dest.join(compressed))?,
+                icon: icon.as_deref(),
+            },
+            &signer,
+        )
+    }
+
+    /// Build the static page that runs the game with love.js
+    fn build_web(&self, dest: &Path, compressed: &str) -> anyhow::Result<()> {
+        let settings = self.config.target.get(&Target::Web);
+
+        let icon = match self.icon(Target::Web) {
+            Some(icon) => {
+                let ext = icon.extension().and_then(|v| v.to_str()).unwrap_or("png");
+                let name = format!("favicon.{ext}");
+                std::fs::copy(&icon, dest.join(&name))?;
+                Some(name)
+            }
+            None => None,
+        };
+
+        web::write(
+            dest,
+            compressed,
+            &web::Page {
+                title: &self.config.project.name,
+                memory: settings
+                    .and_then(|settings| settings.memory)
+                    .unwrap_or(web::DEFAULT_MEMORY),
+                canvas: settings
+                    .and_then(|settings| settings.canvas.as_ref())
+                    .unwrap_or(&Canvas::default()),
+                icon: icon.as_deref(),
+            },
+        )
+    }
+
+    pub fn apply_customizations(&self, target: Target, dest: &Path) -> anyhow::Result<()> {
         match target {
-            // Can only manipulate icon when on windows
-            Target::Win64 if std::env::consts::OS == "windows" => {
+            Target::Win64 => {
+                // Can only manipulate icon when on windows
                 // TODO: Use win32 api to update exe ico
                 // - https://stackoverflow.com/q/67691200
                 // - Image png to ico: https://docs.rs/ico/latest/ico/
                 //      - or https://docs.rs/image/latest/image/index.html to allow it to
                 //      automatically convert the icon file from more formats
             }
-            _ => unimplemented!(),
+            Target::Linux => {
+                let appdir = self.appdir(dest);
+                let name = &self.config.project.name;
+
+                let icon = match self.icon(target) {
+                    Some(icon) => {
+                        let ext = icon.extension().and_then(|v| v.to_str()).unwrap_or("png");
+                        std::fs::copy(&icon, appdir.join(format!("{name}.{ext}")))?;
+
+                        // `.DirIcon` is usually a symlink to the framework icon
+                        let dir_icon = appdir.join(".DirIcon");
+                        if dir_icon.symlink_metadata().is_ok() {
+                            std::fs::remove_file(&dir_icon)?;
+                        }
+                        std::fs::copy(&icon, &dir_icon)?;
+                        true
+                    }
+                    None => false,
+                };
+
+                for entry in std::fs::read_dir(&appdir)?.flatten() {
+                    if let Some("desktop") = entry.path().extension().and_then(|v| v.to_str()) {
+                        let desktop = std::fs::read_to_string(entry.path())?
+                            .lines()
+                            .map(|line| {
+                                if line.starts_with("Name=") {
+                                    format!("Name={name}")
+                                } else if icon && line.starts_with("Icon=") {
+                                    format!("Icon={name}")
+                                } else {
+                                    line.to_string()
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        std::fs::write(entry.path(), desktop + "\n")?;
+                    }
+                }
+            }
+            // Applied while building the app bundle, apk, and page
+            Target::Macos | Target::Android | Target::Web => {}
+            _ => return Err(anyhow::anyhow!("{target} builds are not supported")),
         }
 
         Ok(())
@@ -209,19 +658,245 @@ impl<'conf> Builder<'conf> {
 
     pub fn package(&self, target: Target, dest: &Path) -> anyhow::Result<()> {
         match target {
-            Target::Win64 => {
+            // Web builds are zipped with `index.html` at the root like itch.io expects
+            Target::Win64 | Target::Web => {
                 let mut archive =
                     Archive::new(dest, dest.join(format!("{}.zip", self.config.project.name)))?;
                 archive.add_dir(dest, false)?;
                 archive.finish()?;
             }
-            _ => unimplemented!(),
+            Target::Linux => {
+                let appdir = self.appdir(dest);
+                let name = &self.config.project.name;
+
+                AppImage::open(self.appimage())?
+                    .pack(&appdir, &dest.join(format!("{name}.AppImage")))?;
+
+                // Plain archive for systems that can't run AppImages (no FUSE, containers, etc...)
+                let tarball = std::fs::File::create(dest.join(format!("{name}.tar.gz")))?;
+                let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+                    tarball,
+                    flate2::Compression::default(),
+                ));
+                tar.follow_symlinks(false);
+                tar.append_dir_all(name, &appdir)?;
+                tar.into_inner()?.finish()?;
+            }
+            // The app bundle is zipped when it's built and the apk is the final package
+            Target::Macos | Target::Android => {}
+            _ => return Err(anyhow::anyhow!("{target} builds are not supported")),
         }
 
         Ok(())
     }
 }
 
+/// Resolve the version of the framework a build requires
+///
+/// A version locked in `lbt.lock` that still meets the requirement is used so builds are
+/// reproducible. Otherwise it's the highest release from the build's channel that meets it, or
+/// the highest installed version when offline.
+pub async fn resolve(
+    client: &Client,
+    framework: Framework,
+    build: &Build,
+    lock: Option<&Lock>,
+) -> anyhow::Result<Version> {
+    let (requirement, channel) = (&build.version, build.channel);
+    // Nightlies are named by their run, so `12.0` on the nightly channel is resolved to one
+    if let Some(version) = requirement
+        .exact()
+        .filter(|version| !channel.is_nightly() || version.is_nightly())
+    {
+        return Ok(version);
+    }
+    // Which version a local build is can't be known, so it has to be named
+    if let Some(path) = &build.path {
+        return Err(anyhow::anyhow!(
+            "the local {framework} build {} needs an exact version instead of {requirement}, e.g. `version = \"{}\"` or `lbt run --version`",
+            path.display(),
+            framework.min_version()
+        ));
+    }
+
+    let locked = lock.and_then(|lock| {
+        lock.frameworks
+            .iter()
+            .filter(|locked| {
+                locked.framework == framework && channel.matches(requirement, &locked.version)
+            })
+            .map(|locked| &locked.version)
+            .max()
+    });
+    if let Some(version) = locked {
+        return Ok(version.clone());
+    }
+
+    if client.is_offline() {
+        return Index::load()?
+            .installed
+            .iter()
+            .filter(|install| {
+                install.framework == framework && channel.matches(requirement, &install.version)
+            })
+            .map(|install| install.version.clone())
+            .max()
+            .ok_or(anyhow::anyhow!(
+                "no installed {framework} version matches {requirement} and releases can't be fetched while offline"
+            ));
+    }
+
+    if channel.is_nightly() {
+        let version = nightly::resolve(client, framework).await?;
+        if !channel.matches(requirement, &version) {
+            return Err(anyhow::anyhow!(
+                "the newest {framework} nightly {version} doesn't match {requirement}"
+            ));
+        }
+        return Ok(version);
+    }
+
+    client
+        .releases(framework.owner(), framework.repo())
+        .await?
+        .into_iter()
+        .filter(|release| !release.draft && (channel.is_prerelease() || !release.prerelease))
+        .map(|release| release.tag)
+        .filter(|version| {
+            *version >= framework.min_version() && channel.matches(requirement, version)
+        })
+        .max()
+        .ok_or(anyhow::anyhow!(
+            "no {framework} {channel} release matches {requirement}"
+        ))
+}
+
+/// Install a version of the framework for the target unless it is already installed
+///
+/// A `locked` version is downloaded from where it was locked to without resolving it again.
+/// Any other download is rejected, and an install of another download is replaced.
+pub async fn install(
+    client: &Client,
+    framework: Framework,
+    version: &Version,
+    target: Target,
+    locked: Option<&Locked>,
+    spinner: &mut Progress,
+) -> anyhow::Result<()> {
+    if *version < framework.min_version() {
+        return Err(anyhow::anyhow!(
+            "minimum supported {framework} version is {}",
+            framework.min_version()
+        ));
+    }
+
+    // The web runtime is published separately from the framework's releases
+    if target == Target::Web {
+        if framework != Framework::Love {
+            return Err(anyhow::anyhow!("web builds are only supported for love"));
+        }
+        return web::install(client, version, locked, spinner).await;
+    }
+
+    // Installed versions are resolved without asking github for it's releases
+    let sha256 = locked.map(|locked| locked.sha256.as_str());
+    if Index::load()?
+        .get(framework, target, version)
+        .is_some_and(|install| install.matches(sha256))
+    {
+        return Ok(());
+    }
+
+    if let Some(locked) = locked {
+        let archive = DATA
+            .join(target.to_string())
+            .join(".archive")
+            .join(&locked.asset);
+        if client.is_offline() && !archive.exists() {
+            return Err(offline_error(framework, version, target));
+        }
+
+        return Download {
+            version,
+            asset: &locked.asset,
+            url: &locked.url,
+            size: None,
+            sha256,
+        }
+        .install(client, framework, target, spinner)
+        .await;
+    }
+
+    if version.is_nightly() {
+        return match nightly::install(client, framework, version, target, spinner).await {
+            Err(_) if client.is_offline() => Err(offline_error(framework, version, target)),
+            result => result,
+        };
+    }
+
+    let release = match client
+        .release(framework.owner(), framework.repo(), version)
+        .await
+    {
+        Ok(Some(release)) => release,
+        Ok(None) | Err(_) if client.is_offline() => {
+            return Err(offline_error(framework, version, target))
+        }
+        Ok(None) => {
+            return Err(anyhow::anyhow!(
+                "release version {version} for {framework} was not found"
+            ))
+        }
+        Err(err) => return Err(err),
+    };
+
+    // Offline installs can only unpack a previous download
+    if client.is_offline()
+        && !release
+            .archive(target)
+            .is_some_and(|archive| archive.exists())
+    {
+        return Err(offline_error(framework, version, target));
+    }
+
+    release.install(client, framework, target, spinner).await
+}
+
+/// What an installed version is locked to
+///
+/// Versions installed before downloads were recorded are installed again so they can be locked,
+/// which reuses their previous download.
+pub async fn lock_install(
+    client: &Client,
+    framework: Framework,
+    version: &Version,
+    target: Target,
+    spinner: &mut Progress,
+) -> anyhow::Result<Option<Locked>> {
+    let mut index = Index::load()?;
+    let installed = index.get(framework, target, version).cloned();
+    if let Some(locked) = installed.as_ref().and_then(Install::locked) {
+        return Ok(Some(locked));
+    }
+    if installed.is_none() || client.is_offline() {
+        return Ok(None);
+    }
+
+    index.remove(framework, target, version);
+    index.save()?;
+    install(client, framework, version, target, None, spinner).await?;
+
+    Ok(Index::load()?
+        .get(framework, target, version)
+        .and_then(Install::locked))
+}
+
+fn offline_error(framework: Framework, version: &Version, target: Target) -> anyhow::Error {
+    anyhow::anyhow!(
+        "{framework} version {version} is not installed for target {target} and can't be downloaded while offline"
+    )
+}
+
 struct Archive {
     prefix: PathBuf,
     archive: PathBuf,
@@ -0,0 +1,99 @@
+use crate::{
+    config::{Framework, Target},
+    git::{platform_artifact, Client, Download},
+    Progress, Version,
+};
+
+/// Version of the framework's newest nightly build
+///
+/// Nightlies are the artifacts of the newest successful workflow run on the framework's
+/// development branch. They are versioned as prereleases of the version after the newest
+/// release, e.g. `12.0-nightly.<run>+<commit>` while `11.5` is the newest, so newer runs sort
+/// higher.
+pub async fn resolve(client: &Client, framework: Framework) -> anyhow::Result<Version> {
+    let next = next_version(client, framework).await?;
+    let (owner, repo) = (framework.owner(), framework.repo());
+
+    for run in client.runs(owner, repo, framework.nightly_branch()).await? {
+        let artifacts = client.artifacts(owner, repo, run.id).await?;
+        // Runs of workflows that don't upload builds, e.g. linting, are skipped
+        if artifacts
+            .iter()
+            .any(|artifact| !artifact.expired && artifact.target().is_some())
+        {
+            return Ok(Version {
+                pre: Some(format!("nightly.{}", run.id)),
+                build: Some(run.head_sha.chars().take(7).collect()),
+                ..next
+            });
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "no nightly builds of {framework} found on it's `{}` branch",
+        framework.nightly_branch()
+    ))
+}
+
+/// Install the nightly build of `version` for the target
+pub async fn install(
+    client: &Client,
+    framework: Framework,
+    version: &Version,
+    target: Target,
+    spinner: &mut Progress,
+) -> anyhow::Result<()> {
+    let run = version.nightly_run().ok_or(anyhow::anyhow!(
+        "{framework} {version} is not a nightly build"
+    ))?;
+    let (owner, repo) = (framework.owner(), framework.repo());
+
+    spinner.update(format!("resolving {framework} {version} for {target}"));
+    let artifacts = client.artifacts(owner, repo, run).await?;
+    let artifact = platform_artifact(&artifacts, target).ok_or(anyhow::anyhow!(
+        "no {framework} {version} build for target: {target}"
+    ))?;
+    if artifact.expired {
+        return Err(anyhow::anyhow!(
+            "{framework} {version} for {target} has expired and can no longer be downloaded"
+        ));
+    }
+
+    Download {
+        version,
+        // Artifacts are named the same by every run
+        asset: &format!("{}-{run}.zip", artifact.name),
+        url: &client.artifact_url(owner, repo, run, artifact)?,
+        size: None,
+        sha256: None,
+    }
+    .install(client, framework, target, spinner)
+    .await
+}
+
+/// Version that comes after the framework's newest release
+///
+/// The major version is bumped, or the minor one before `1.0`, like the next breaking release.
+async fn next_version(client: &Client, framework: Framework) -> anyhow::Result<Version> {
+    let latest = client
+        .releases(framework.owner(), framework.repo())
+        .await?
+        .into_iter()
+        .filter(|release| !release.draft && !release.prerelease && !release.tag.is_prerelease())
+        .map(|release| release.tag)
+        .max()
+        .ok_or(anyhow::anyhow!("no {framework} releases found"))?;
+
+    let (major, minor) = match latest.major {
+        0 => (0, latest.minor + 1),
+        major => (major + 1, 0),
+    };
+    Ok(Version {
+        major,
+        minor,
+        // Follow the framework's tags, e.g. love's `11.5` and lovr's `0.17.1`
+        patch: latest.patch.map(|_| 0),
+        pre: None,
+        build: None,
+    })
+}
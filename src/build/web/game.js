@@ -0,0 +1,26 @@
+// Loads `game.data` into the file system as `/game.love` before love.js starts
+var Module = typeof Module !== "undefined" ? Module : {};
+
+(function () {
+  var data = fetch("game.data").then(function (response) {
+    if (!response.ok) {
+      throw new Error(response.status + " : " + response.url);
+    }
+    return response.arrayBuffer();
+  });
+
+  Module.preRun = Module.preRun || [];
+  Module.preRun.push(function () {
+    Module.addRunDependency("datafile_game.data");
+    data.then(
+      function (buffer) {
+        Module.FS_createDataFile("/", "game.love", new Uint8Array(buffer), true, true, true);
+        Module.removeRunDependency("datafile_game.data");
+      },
+      function (error) {
+        Module.setStatus("Failed to load game.data");
+        Module.printErr(error);
+      }
+    );
+  });
+})();
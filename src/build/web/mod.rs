@@ -0,0 +1,288 @@
+use std::{collections::BTreeMap, io::Read, path::Path, str::FromStr};
+
+use serde::Deserialize;
+
+use crate::{
+    checksum,
+    config::{Canvas, Framework, Target},
+    git::Client,
+    index::{Index, Install},
+    lock::Locked,
+    Progress, SpinnerError, Version, DATA,
+};
+
+/// npm package love.js is published as
+const PACKAGE: &str = "love.js";
+/// Files of the runtime that are copied next to the game
+const RUNTIME: [&str; 3] = ["love.js", "love.wasm", "love.worker.js"];
+
+/// Initial memory used by love.js when it isn't configured
+pub const DEFAULT_MEMORY: u64 = 16 * 1024 * 1024;
+
+const INDEX: &str = include_str!("index.html");
+const LOADER: &str = include_str!("game.js");
+
+#[derive(Deserialize)]
+struct Package {
+    versions: BTreeMap<String, PackageVersion>,
+}
+
+#[derive(Deserialize)]
+struct PackageVersion {
+    dist: Dist,
+}
+
+#[derive(Deserialize)]
+struct Dist {
+    tarball: String,
+}
+
+/// Install the love.js runtime for `version` of love
+///
+/// love.js follows love's major version so the newest runtime with the same major version
+/// is used. It is installed next to the framework's other targets for the version.
+///
+/// A `locked` runtime is used instead of the newest one. When offline only a runtime that was
+/// downloaded before can be installed.
+pub async fn install(
+    client: &Client,
+    version: &Version,
+    locked: Option<&Locked>,
+    spinner: &mut Progress,
+) -> anyhow::Result<()> {
+    let sha256 = locked.map(|locked| locked.sha256.as_str());
+    let mut index = Index::load()?;
+    if index
+        .get(Framework::Love, Target::Web, version)
+        .is_some_and(|install| install.matches(sha256))
+    {
+        return Ok(());
+    }
+    let dir = Framework::Love.web_runtime(version);
+    let offline = || super::offline_error(Framework::Love, version, Target::Web);
+
+    let archive_path = DATA.join(Target::Web.to_string()).join(".archive");
+    let (runtime, url) = match locked {
+        Some(locked) => (
+            runtime(&locked.asset)
+                .ok_or(anyhow::anyhow!("{} is not a love.js runtime", locked.asset))?,
+            Some(locked.url.clone()),
+        ),
+        None if client.is_offline() => {
+            let runtime = archived(&archive_path, version).ok_or_else(offline)?;
+            let url = index
+                .url(Target::Web, &format!("love.js-{runtime}.tgz"))
+                .map(str::to_string);
+            (runtime, url)
+        }
+        None => {
+            let (runtime, url) = resolve(client, version, spinner).await?;
+            (runtime, Some(url))
+        }
+    };
+
+    let name = format!("love.js-{runtime}.tgz");
+    let tarball = archive_path.join(&name);
+    match &url {
+        Some(_) if client.is_offline() && !tarball.exists() => return Err(offline()),
+        Some(url) => {
+            download(client, &index, &tarball, url, sha256, spinner).await?;
+        }
+        None => {}
+    }
+
+    let checksum = checksum::verify_file(
+        &tarball,
+        None,
+        sha256.or(index.checksum(Target::Web, &name)),
+    )
+    .log_err_in_spin(spinner, "love.js runtime is corrupt")?;
+
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+
+    // The package keeps the runtimes in `src/compat` and `src/release`
+    let mut archive =
+        tar::Archive::new(flate2::read::GzDecoder::new(std::fs::File::open(&tarball)?));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+
+        let (Some(file), Some(variant)) = (
+            path.file_name().and_then(|v| v.to_str()),
+            path.parent()
+                .and_then(|v| v.file_name())
+                .and_then(|v| v.to_str()),
+        ) else {
+            continue;
+        };
+        if !RUNTIME.contains(&file) || !matches!(variant, "compat" | "release") {
+            continue;
+        }
+
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        std::fs::create_dir_all(dir.join(variant))?;
+        std::fs::write(dir.join(variant).join(file), content)?;
+        spinner.log(format!(" └ unpacked {variant}/{file}"));
+    }
+
+    if !dir.join("compat").join("love.wasm").exists() {
+        return Err(anyhow::anyhow!(
+            "love.js {runtime} does not contain a compatibility runtime"
+        ));
+    }
+
+    index.insert(Install {
+        framework: Framework::Love,
+        target: Target::Web,
+        version: version.clone(),
+        asset: name,
+        url,
+        sha256: Some(checksum),
+    });
+    index
+        .save()
+        .log_err_in_spin(spinner, "failed to save install index")?;
+    spinner.success(format!("Installed love.js {runtime} for love {version}"));
+
+    Ok(())
+}
+
+/// Resolve the newest runtime for `version` from the registry, returning it's tarball
+async fn resolve(
+    client: &Client,
+    version: &Version,
+    spinner: &mut Progress,
+) -> anyhow::Result<(Version, String)> {
+    spinner.update("resolving love.js runtime");
+    let package: Package = client
+        .get_cached(format!("{}/{PACKAGE}", client.registry()))
+        .await?;
+
+    package
+        .versions
+        .into_iter()
+        .filter_map(|(v, release)| Version::from_str(&v).ok().map(|v| (v, release)))
+        .filter(|(v, _)| v.major == version.major && !v.is_prerelease())
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(runtime, release)| (runtime, release.dist.tarball))
+        .ok_or(anyhow::anyhow!(
+            "no love.js runtime found for love {version}"
+        ))
+}
+
+/// Download a runtime's tarball from `url`
+///
+/// A previous download that doesn't match `sha256` or it's recorded checksum is downloaded again
+async fn download(
+    client: &Client,
+    index: &Index,
+    tarball: &Path,
+    url: &str,
+    sha256: Option<&str>,
+    spinner: &mut Progress,
+) -> anyhow::Result<()> {
+    let name = tarball
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let expected = sha256.or(index.checksum(Target::Web, &name));
+    if tarball.exists() {
+        if let Err(err) = checksum::verify_file(tarball, None, expected) {
+            spinner.log(format!(" └ {err}, downloading it again"));
+            std::fs::remove_file(tarball)?;
+        }
+    }
+
+    if !tarball.exists() {
+        let archive_path = tarball.parent().unwrap();
+        std::fs::create_dir_all(archive_path).log_err_in_spin(
+            spinner,
+            format!("failed to create directory {}", archive_path.display()),
+        )?;
+
+        spinner.update(format!("installing {name}"));
+        client
+            .download(url, tarball, spinner)
+            .await
+            .log_err_in_spin(spinner, "failed to download love.js")?;
+    }
+
+    Ok(())
+}
+
+/// Version of the runtime in a downloaded tarball's name
+fn runtime(asset: &str) -> Option<Version> {
+    let runtime = asset.strip_prefix("love.js-")?.strip_suffix(".tgz")?;
+    Version::from_str(runtime).ok()
+}
+
+/// Newest runtime for `version` that was already downloaded to `archive_path`
+fn archived(archive_path: &Path, version: &Version) -> Option<Version> {
+    std::fs::read_dir(archive_path)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| runtime(entry.file_name().to_str()?))
+        .filter(|runtime| runtime.major == version.major && !runtime.is_prerelease())
+        .max()
+}
+
+/// Copy the runtime variant to `dest`
+pub fn copy_runtime(dir: &Path, threads: bool, dest: &Path) -> anyhow::Result<()> {
+    let variant = dir.join(if threads { "release" } else { "compat" });
+    if !variant.exists() {
+        return Err(anyhow::anyhow!(
+            "love.js runtime not found at {}",
+            variant.display()
+        ));
+    }
+
+    for file in RUNTIME {
+        if variant.join(file).exists() {
+            std::fs::copy(variant.join(file), dest.join(file))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Page the game is embedded in
+pub struct Page<'a> {
+    pub title: &'a str,
+    pub memory: u64,
+    pub canvas: &'a Canvas,
+    /// File name of the page's icon
+    pub icon: Option<&'a str>,
+}
+
+/// Write `game.data`, it's loader, and `index.html` in the layout love.js expects
+///
+/// `game.data` is the compressed game itself which the loader mounts as `/game.love`.
+pub fn write(dest: &Path, compressed: &str, page: &Page) -> anyhow::Result<()> {
+    std::fs::rename(dest.join(compressed), dest.join("game.data"))?;
+    std::fs::write(dest.join("game.js"), LOADER)?;
+
+    let icon = page
+        .icon
+        .map(|icon| format!("\n    <link rel=\"icon\" href=\"{}\">", escape(icon)))
+        .unwrap_or_default();
+    let index = INDEX
+        .replace("{{title}}", &escape(page.title))
+        .replace("{{icon}}", &icon)
+        .replace("{{width}}", &page.canvas.width.to_string())
+        .replace("{{height}}", &page.canvas.height.to_string())
+        .replace("{{memory}}", &page.memory.to_string());
+    std::fs::write(dest.join("index.html"), index)?;
+
+    Ok(())
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,56 @@
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+/// SHA-256 of a file as lowercase hex, read without loading it into memory
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Check a download against the size listed for it and a checksum it was pinned to
+///
+/// Either can be missing, e.g. for archives downloaded before checksums were recorded.
+pub fn verify(
+    name: &str,
+    size: u64,
+    sha256: &str,
+    expected_size: Option<u64>,
+    expected_sha256: Option<&str>,
+) -> anyhow::Result<()> {
+    if let Some(expected) = expected_size.filter(|expected| *expected != size) {
+        return Err(anyhow::anyhow!(
+            "{name} is {size} bytes but should be {expected} bytes"
+        ));
+    }
+    if let Some(expected) =
+        expected_sha256.filter(|expected| !expected.eq_ignore_ascii_case(sha256))
+    {
+        return Err(anyhow::anyhow!(
+            "{name} has checksum {sha256} but {expected} was expected"
+        ));
+    }
+    Ok(())
+}
+
+/// Checksum of a previous download, checked like [`verify`]
+pub fn verify_file(
+    path: &Path,
+    expected_size: Option<u64>,
+    expected_sha256: Option<&str>,
+) -> anyhow::Result<String> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let sha256 = sha256_file(path)?;
+    verify(
+        &name,
+        path.metadata()?.len(),
+        &sha256,
+        expected_size,
+        expected_sha256,
+    )?;
+    Ok(sha256)
+}
@@ -1,8 +1,12 @@
-use std::{collections::BTreeMap, path::PathBuf, str::FromStr};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{Version, DATA};
+use crate::{Requirement, Version, DATA};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Config {
@@ -11,23 +15,39 @@ pub struct Config {
     pub build: BTreeMap<Framework, Build>,
     #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
     pub target: BTreeMap<Target, Settings>,
+    #[serde(default, skip_serializing_if = "Mirror::is_empty")]
+    pub mirror: Mirror,
+    #[serde(default, skip_serializing_if = "Cache::is_empty")]
+    pub cache: Cache,
+    #[serde(default, skip_serializing_if = "Run::is_empty")]
+    pub run: Run,
 }
 
 impl Config {
+    /// Parse the `lbt.toml` of the project at `root`
+    pub fn parse(root: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(root.join("lbt.toml"))?;
+        Ok(toml::from_str(content.as_str())?)
+    }
+
     pub fn parse_or_default() -> anyhow::Result<Self> {
         let cd = std::env::current_dir()?;
 
         if cd.join("lbt.toml").exists() {
-            let content = std::fs::read_to_string(cd.join("lbt.toml"))?;
-            Ok(toml::from_str(content.as_str())?)
+            Self::parse(&cd)
         } else {
             Ok(Self {
                 project: Project {
                     name: cd.file_name().unwrap().to_str().unwrap().to_string(),
+                    version: None,
+                    identifier: None,
                     icon: None,
                 },
                 build: BTreeMap::default(),
                 target: BTreeMap::default(),
+                mirror: Mirror::default(),
+                cache: Cache::default(),
+                run: Run::default(),
             })
         }
     }
@@ -36,10 +56,15 @@ impl Config {
         Self {
             project: Project {
                 name: name.to_string(),
+                version: None,
+                identifier: None,
                 icon: None,
             },
             build: BTreeMap::default(),
             target: BTreeMap::default(),
+            mirror: Mirror::default(),
+            cache: Cache::default(),
+            run: Run::default(),
         }
     }
 }
@@ -50,11 +75,47 @@ pub struct Project {
     ///
     /// This is used when naming final executables and directories
     pub name: String,
+    /// Version of the project
+    ///
+    /// Defaults to `0.1.0`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<Version>,
+    /// Reverse domain name identifier of the project, e.g. `com.example.game`
+    ///
+    /// Used as the macOS bundle identifier and the android package name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<String>,
     /// Icon to use when a more specific icon is not specified
     #[serde(skip_serializing_if = "Option::is_none")]
     pub icon: Option<String>,
 }
 
+impl Project {
+    pub fn version(&self) -> Version {
+        self.version.clone().unwrap_or(Version {
+            major: 0,
+            minor: 1,
+            patch: Some(0),
+            pre: None,
+            build: None,
+        })
+    }
+
+    /// Identifier of the project, defaulting to `com.lbt.<name>`
+    pub fn identifier(&self) -> String {
+        self.identifier.clone().unwrap_or_else(|| {
+            format!(
+                "com.lbt.{}",
+                self.name
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric())
+                    .collect::<String>()
+                    .to_ascii_lowercase()
+            )
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "kebab-case")]
 pub enum Framework {
@@ -104,30 +165,40 @@ impl Framework {
         }
     }
 
+    /// Branch the framework's development builds are made from
     #[inline]
-    pub const fn min_version(&self) -> Version {
+    pub const fn nightly_branch(&self) -> &str {
         match self {
-            Self::Love => Version::min_love_version(),
-            Self::Lovr => Version::min_lovr_version(),
+            Self::Love => "main",
+            Self::Lovr => "dev",
         }
     }
 
     #[inline]
-    pub const fn latest(&self) -> Version {
+    pub const fn min_version(&self) -> Version {
         match self {
-            Self::Love => Version::latest_love_version(),
-            Self::Lovr => Version::latest_lovr_version(),
+            Self::Love => Version::min_love_version(),
+            Self::Lovr => Version::min_lovr_version(),
         }
     }
 
+    /// Directory a version of the framework is installed to for the target
+    ///
+    /// Versions are installed side by side as `DATA/<target>/<framework>/<version>`, named by
+    /// their normalized version so `11.5` and `11.5.0` share a directory
     #[inline]
-    pub fn path(&self, target: Target) -> PathBuf {
-        DATA.join(target.to_string()).join(self.to_string())
+    pub fn path(&self, target: Target, version: &Version) -> PathBuf {
+        DATA.join(target.to_string())
+            .join(self.to_string())
+            .join(version.normalized())
     }
 
+    /// love.js runtime of the framework that is installed for the web
+    ///
+    /// Contains a `compat` and a `release` (multithreaded) build of the runtime
     #[inline]
-    pub fn exe(&self, target: Target) -> PathBuf {
-        self.path(target).join(format!("{self}.exe"))
+    pub fn web_runtime(&self, version: &Version) -> PathBuf {
+        self.path(Target::Web, version)
     }
 
     #[inline]
@@ -147,10 +218,22 @@ impl Framework {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct Build {
-    /// Version to use of the framework when building
-    pub version: Version,
+    /// Versions of the framework the project can be built with
+    ///
+    /// The version that is used is recorded in `lbt.lock`
+    pub version: Requirement,
+    /// Which builds of the framework the version is resolved from
+    #[serde(default, skip_serializing_if = "Channel::is_stable")]
+    pub channel: Channel,
+    /// Local build of the framework used in place of a downloaded one, e.g. `../love/build`
+    ///
+    /// Relative to the project and laid out like an installed version, with `love.exe` and
+    /// it's libraries, `love.AppImage`, `love.app.zip`, or `love.apk` for the targets built.
+    /// The build's `version` has to be exact, since which version it is can't be told.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
     /// Optional list of targets to build for.
     ///
     /// Defaults to only building for the current OS
@@ -158,7 +241,47 @@ pub struct Build {
     pub targets: Vec<Target>,
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+/// Builds of a framework a project opts into
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, strum::EnumIs)]
+#[serde(rename_all = "kebab-case")]
+pub enum Channel {
+    /// Releases only
+    #[default]
+    Stable,
+    /// Releases and prereleases, e.g. `12.0-rc1`
+    Prerelease,
+    /// Development builds made by the framework's CI, e.g. `12.0-nightly.<run>+<commit>`
+    Nightly,
+}
+
+impl Channel {
+    /// Whether `version` is from the channel and meets the requirement
+    pub fn matches(&self, requirement: &Requirement, version: &Version) -> bool {
+        match self {
+            Self::Stable => requirement.matches(version),
+            Self::Prerelease => !version.is_nightly() && requirement.matches_prerelease(version),
+            Self::Nightly => version.is_nightly() && requirement.matches_prerelease(version),
+        }
+    }
+}
+
+impl std::fmt::Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Stable => "stable",
+                Self::Prerelease => "prerelease",
+                Self::Nightly => "nightly",
+            }
+        )
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, strum::EnumIter,
+)]
 #[serde(rename_all = "kebab-case")]
 pub enum Target {
     Win64,
@@ -166,8 +289,11 @@ pub enum Target {
     Linux,
     Ios,
     Android,
+    Web,
 }
 
+// The default depends on the host os so it can't be derived
+#[allow(clippy::derivable_impls)]
 impl Default for Target {
     fn default() -> Self {
         #[cfg(target_os = "windows")]
@@ -204,14 +330,196 @@ impl std::fmt::Display for Target {
                 Self::Linux => "linux",
                 Self::Ios => "ios",
                 Self::Android => "android",
+                Self::Web => "web",
             }
         )
     }
 }
 
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "win64" | "windows" => Ok(Self::Win64),
+            "macos" => Ok(Self::Macos),
+            "linux" => Ok(Self::Linux),
+            "ios" => Ok(Self::Ios),
+            "android" => Ok(Self::Android),
+            "web" => Ok(Self::Web),
+            other => Err(format!("invalid target: {other}")),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
 pub struct Settings {
     /// Specific icon to use when building for the specific target (OS)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub icon: Option<String>,
+    /// Keystore used to sign the build (android only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keystore: Option<Keystore>,
+    /// Version code of the build (android only)
+    ///
+    /// Defaults to `major * 10000 + minor * 100 + patch` of the project version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_code: Option<u32>,
+    /// Initial memory of the game in bytes (web only)
+    ///
+    /// Defaults to 16 MiB, the same as love.js
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<u64>,
+    /// Canvas the game is drawn to (web only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canvas: Option<Canvas>,
+    /// Use the multithreaded love.js runtime (web only)
+    ///
+    /// Defaults to the compatibility runtime. The threaded runtime needs `SharedArrayBuffer`
+    /// which browsers only allow when the page is served with cross-origin isolation headers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threads: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for Canvas {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Keystore {
+    /// Path to a PKCS#12 keystore relative to the project root
+    pub path: String,
+    /// Alias of the key to sign with
+    ///
+    /// Defaults to the first key in the keystore
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// Environment variable holding the keystore password
+    #[serde(default = "Keystore::default_password_env")]
+    pub password_env: String,
+}
+
+impl Keystore {
+    fn default_password_env() -> String {
+        "LBT_KEYSTORE_PASSWORD".to_string()
+    }
+}
+
+/// Settings of `lbt run`
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Run {
+    /// Framework `lbt run` runs when several are configured
+    ///
+    /// Defaults to the first one, `love` before `lovr`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub framework: Option<Framework>,
+    /// Directories `lbt run --watch` watches besides `src`, relative to the project
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub watch: Vec<PathBuf>,
+    /// Open the game's window where it was before `lbt run --watch` restarted it
+    ///
+    /// Only supported for love
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub keep_window: bool,
+}
+
+impl Run {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Mirrors used in place of GitHub and npm when installing frameworks
+///
+/// Each url can also be set with an environment variable, `LBT_MIRROR_API`,
+/// `LBT_MIRROR_DOWNLOADS`, `LBT_MIRROR_REGISTRY`, and `LBT_MIRROR_NIGHTLY`, which take priority
+/// over `lbt.toml`.
+/// `file://` urls point to a plain directory with the same layout.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Mirror {
+    /// Base url of the releases api used in place of `https://api.github.com`
+    ///
+    /// Releases are listed from `<api>/repos/<owner>/<repo>/releases`, e.g. a GitHub
+    /// Enterprise api or a directory with the releases json at that path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api: Option<String>,
+    /// Base url that downloads from `https://github.com` are sent to instead
+    ///
+    /// Assets are downloaded from `<downloads>/<owner>/<repo>/releases/download/<tag>/<asset>`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<String>,
+    /// Base url of the npm registry used in place of `https://registry.npmjs.org`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Base url nightly builds are downloaded from in place of GitHub's artifact downloads
+    ///
+    /// Artifacts are downloaded from `<nightly>/<owner>/<repo>/actions/runs/<run>/<artifact>.zip`,
+    /// which is the layout of `https://nightly.link`. GitHub only lets authenticated requests
+    /// download artifacts, so this is needed for nightlies when no token is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nightly: Option<String>,
+}
+
+impl Mirror {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Override the mirrors with the ones set in the environment
+    pub fn with_env(mut self) -> Self {
+        for (var, url) in [
+            ("LBT_MIRROR_API", &mut self.api),
+            ("LBT_MIRROR_DOWNLOADS", &mut self.downloads),
+            ("LBT_MIRROR_REGISTRY", &mut self.registry),
+            ("LBT_MIRROR_NIGHTLY", &mut self.nightly),
+        ] {
+            if let Ok(value) = std::env::var(var) {
+                *url = Some(value).filter(|v| !v.is_empty());
+            }
+        }
+        self
+    }
+}
+
+/// Settings of the cache of GitHub and npm responses
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Cache {
+    /// Seconds a cached response is used before it's requested again, an hour by default
+    ///
+    /// Can also be set with `LBT_CACHE_TTL`, which takes priority over `lbt.toml`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+}
+
+impl Cache {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Override the settings with the ones set in the environment
+    pub fn with_env(mut self) -> anyhow::Result<Self> {
+        if let Ok(ttl) = std::env::var("LBT_CACHE_TTL") {
+            self.ttl = Some(ttl.parse().map_err(|_| {
+                anyhow::anyhow!("LBT_CACHE_TTL must be a number of seconds: {ttl}")
+            })?);
+        }
+        Ok(self)
+    }
 }
@@ -0,0 +1,205 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::{Component, Path, PathBuf},
+};
+
+use zip::ZipArchive;
+
+/// Extract a zip archive to `dest`, keeping it's directory structure
+///
+/// Archives usually keep everything in one folder named after the archive, which is stripped so
+/// `dest` holds it's contents. Symlinks and unix modes are kept, and entries that would end up
+/// outside of `dest` fail the extraction.
+///
+/// Returns the paths of the extracted files relative to `dest`.
+pub fn unzip(archive: &Path, dest: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    unzip_entries(archive, None, dest)
+}
+
+/// Extract the contents of the archive's directory `dir` to `dest`, e.g. a bundle's `love.app`
+///
+/// Entries outside of `dir` are skipped, and the same checks as [`unzip`] apply.
+pub fn unzip_dir(archive: &Path, dir: &str, dest: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    unzip_entries(archive, Some(relative(dir)?), dest)
+}
+
+fn unzip_entries(
+    archive: &Path,
+    dir: Option<PathBuf>,
+    dest: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(archive)?))?;
+
+    let mut entries = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        let path = relative(file.name())?;
+        // Finder metadata, which isn't part of what was archived
+        if path.starts_with("__MACOSX") {
+            continue;
+        }
+        entries.push((i, path, file.is_dir()));
+    }
+
+    std::fs::create_dir_all(dest)?;
+    let root = dest.canonicalize()?;
+    let top = dir.or_else(|| top_level(&entries));
+    let mut extracted = Vec::new();
+    let mut directories = Vec::new();
+    let mut links = Vec::new();
+    for (i, path, is_dir) in entries {
+        let path = match &top {
+            Some(top) => match path.strip_prefix(top) {
+                Ok(path) => path.to_path_buf(),
+                Err(_) => continue,
+            },
+            None => path,
+        };
+        if path.as_os_str().is_empty() {
+            continue;
+        }
+
+        let mut file = archive.by_index(i)?;
+        let out = dest.join(&path);
+        if is_dir {
+            std::fs::create_dir_all(&out)?;
+            directories.push((out, file.unix_mode()));
+            continue;
+        }
+
+        if let Some(parent) = out.parent() {
+            std::fs::create_dir_all(parent)?;
+            // Catches links that were already in `dest`, the archive's own are created last
+            if !parent.canonicalize()?.starts_with(&root) {
+                return Err(anyhow::anyhow!(
+                    "{} would be extracted outside of the archive",
+                    file.name()
+                ));
+            }
+        }
+        if file.is_symlink() {
+            let mut target = String::new();
+            file.read_to_string(&mut target)?;
+            if !inside(&path, Path::new(&target)) {
+                return Err(anyhow::anyhow!(
+                    "{} links to {target} which is outside of the archive",
+                    file.name()
+                ));
+            }
+            links.push((file.name().to_string(), target, out));
+        } else {
+            std::io::copy(&mut file, &mut File::create(&out)?)?;
+            set_mode(&out, file.unix_mode())?;
+        }
+        extracted.push(path);
+    }
+
+    // Symlinks are created once every file is written so none are written through them, and
+    // are checked once they all exist since a chain of them can lead outside of `dest`
+    for (name, target, out) in &links {
+        if out.symlink_metadata().is_ok_and(|meta| meta.is_dir()) {
+            return Err(anyhow::anyhow!(
+                "{name} links to {target} but other entries are extracted inside of it"
+            ));
+        }
+        symlink(target, out)?;
+    }
+    for (name, target, out) in &links {
+        if !out.canonicalize().is_ok_and(|path| path.starts_with(&root)) {
+            return Err(anyhow::anyhow!(
+                "{name} links to {target} which is outside of the archive"
+            ));
+        }
+    }
+
+    // Modes of directories are set last so read-only ones can still be extracted into
+    for (directory, mode) in directories.into_iter().rev() {
+        set_mode(&directory, mode)?;
+    }
+
+    Ok(extracted)
+}
+
+/// Path of an entry, rejecting ones that leave the directory they are extracted to
+fn relative(name: &str) -> anyhow::Result<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "{name} would be extracted outside of the archive"
+                ))
+            }
+        }
+    }
+    Ok(path)
+}
+
+/// Folder every entry is in, if there is only one
+fn top_level(entries: &[(usize, PathBuf, bool)]) -> Option<PathBuf> {
+    let mut top = None;
+    for (_, path, is_dir) in entries {
+        let mut components = path.components();
+        let first = PathBuf::from(components.next()?.as_os_str());
+        // A file at the top level can't be stripped
+        if !is_dir && components.next().is_none() {
+            return None;
+        }
+
+        match &top {
+            None => top = Some(first),
+            Some(top) if *top != first => return None,
+            Some(_) => {}
+        }
+    }
+    top
+}
+
+/// Whether a symlink at `path` pointing to `target` stays inside of the archive
+fn inside(path: &Path, target: &Path) -> bool {
+    let mut depth = path.components().count() - 1;
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return false,
+        }
+    }
+    true
+}
+
+#[cfg(unix)]
+fn symlink(target: &str, link: &Path) -> std::io::Result<()> {
+    if link.symlink_metadata().is_ok() {
+        std::fs::remove_file(link)?;
+    }
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Creating symlinks needs extra privileges on windows, so the link is written as a file
+/// holding it's target like git does
+#[cfg(not(unix))]
+fn symlink(target: &str, link: &Path) -> std::io::Result<()> {
+    std::fs::write(link, target)
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: Option<u32>) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    match mode {
+        Some(mode) => {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o7777))
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: Option<u32>) -> std::io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use serde::Deserialize;
+
+use crate::config::Target;
+
+/// Workflow run of a repo's CI
+#[derive(Debug, Clone, Deserialize)]
+pub struct Run {
+    pub id: u64,
+    pub run_number: usize,
+    pub head_branch: Option<String>,
+    pub head_sha: String,
+    pub status: Option<String>,
+    pub conclusion: Option<String>,
+    pub created_at: String,
+    pub artifacts_url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct Runs {
+    pub workflow_runs: Vec<Run>,
+}
+
+/// Files uploaded by a workflow run, which GitHub serves as a zip
+#[derive(Debug, Clone, Deserialize)]
+pub struct Artifact {
+    pub id: u64,
+    pub name: String,
+    pub size_in_bytes: u64,
+    pub expired: bool,
+    pub archive_download_url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct Artifacts {
+    pub artifacts: Vec<Artifact>,
+}
+
+impl Artifact {
+    /// Target the artifact is a build for, going by it's name
+    ///
+    /// Debug symbols and 32 bit or arm builds aren't for any target.
+    pub fn target(&self) -> Option<Target> {
+        let name = self.name.to_lowercase();
+        let words = name
+            .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .collect::<Vec<_>>();
+        let has = |word: &str| words.contains(&word);
+
+        if [
+            "dbg", "debug", "symbols", "pdb", "win32", "x86", "arm64", "aarch64",
+        ]
+        .into_iter()
+        .any(has)
+        {
+            return None;
+        }
+
+        if has("android") || has("apk") {
+            Some(Target::Android)
+        } else if has("ios") {
+            Some(Target::Ios)
+        } else if has("macos") || has("osx") || has("mac") {
+            Some(Target::Macos)
+        } else if has("linux") || has("appimage") {
+            Some(Target::Linux)
+        } else if has("win64") || has("windows") || has("win") || has("x64") {
+            Some(Target::Win64)
+        } else {
+            None
+        }
+    }
+
+    /// Build meant to have a game embedded into it, e.g. `love-android-embed`
+    pub fn is_embed(&self) -> bool {
+        self.name
+            .to_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|word| word == "embed")
+    }
+}
+
+/// Artifact of the run that is installed for the target
+///
+/// Android games are built from the embed build, e.g. `love-android-embed` over
+/// `love-android`. Otherwise the one with the shortest name is used when several artifacts are
+/// for the target.
+pub fn platform_artifact(artifacts: &[Artifact], target: Target) -> Option<&Artifact> {
+    artifacts
+        .iter()
+        .filter(|artifact| artifact.target() == Some(target))
+        .min_by_key(|artifact| {
+            (
+                target == Target::Android && !artifact.is_embed(),
+                artifact.name.len(),
+            )
+        })
+}
@@ -0,0 +1,110 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// On-disk cache of api responses
+///
+/// Responses are used as is until they are older than the ttl, after which they are
+/// revalidated with their `ETag` so an unchanged response doesn't count against the rate limit.
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Entry {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// Url of the next page of the response from it's `Link` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    /// Unix time in seconds the response was fetched or last revalidated
+    pub fetched: u64,
+    pub body: String,
+}
+
+impl Entry {
+    pub fn new(
+        url: impl Into<String>,
+        etag: Option<String>,
+        next: Option<String>,
+        body: String,
+    ) -> Self {
+        Self {
+            url: url.into(),
+            etag,
+            next,
+            fetched: now(),
+            body,
+        }
+    }
+
+    /// Whether the response can be used without revalidating it
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        now().saturating_sub(self.fetched) < ttl.as_secs()
+    }
+
+    /// Mark the response as revalidated
+    pub fn touch(&mut self) {
+        self.fetched = now();
+    }
+}
+
+impl Cache {
+    /// How long responses are used before they are revalidated when not configured
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    #[inline]
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        self.ttl = ttl;
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir
+            .join(format!("{:x}.json", Sha256::digest(url.as_bytes())))
+    }
+
+    /// Cached response for the url, fresh or not
+    ///
+    /// Unreadable entries are treated as missing so they are fetched again
+    pub fn get(&self, url: &str) -> Option<Entry> {
+        let content = std::fs::read_to_string(self.path(url)).ok()?;
+        serde_json::from_str::<Entry>(&content)
+            .ok()
+            .filter(|entry| entry.url == url)
+    }
+
+    pub fn put(&self, entry: &Entry) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+
+        // Written to a temporary file first so a concurrent build never reads half an entry
+        let path = self.path(&entry.url);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string(entry)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|v| v.as_secs())
+        .unwrap_or(0)
+}
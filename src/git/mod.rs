@@ -1,59 +1,475 @@
-use std::collections::BTreeMap;
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    time::{Duration, Instant},
+};
 
-use reqwest::{IntoUrl, RequestBuilder};
+use reqwest::{header, RequestBuilder, StatusCode, Url};
+use serde::de::DeserializeOwned;
 
+use crate::{config::Mirror, Progress, Version, DATA};
+
+mod actions;
+mod cache;
 mod release;
 
-pub use release::{Asset, AssetName, AssetType, Author, Release};
+pub use actions::{platform_artifact, Artifact, Run};
+pub use cache::{Cache, Entry};
+pub use release::{Asset, AssetName, AssetType, Author, Download, Release};
 
 pub struct Client {
     user_agent: String,
+    cache: Cache,
+    offline: bool,
+    mirror: Mirror,
+    token: Option<String>,
 }
 
 impl Client {
     const GITHUB_API: &'static str = "https://api.github.com";
+    const GITHUB: &'static str = "https://github.com";
+    const NPM_REGISTRY: &'static str = "https://registry.npmjs.org";
+    /// Times a download is attempted before giving up
+    const DOWNLOAD_ATTEMPTS: u32 = 5;
+    /// How long a download can go without receiving anything
+    const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
 
+    /// Client with responses cached in `DATA/cache/http` for [`Cache::DEFAULT_TTL`]
     pub fn new(user_agent: impl AsRef<str>) -> Self {
         Self {
             user_agent: user_agent.as_ref().to_string(),
+            cache: Cache::new(DATA.join("cache").join("http"), Cache::DEFAULT_TTL),
+            offline: false,
+            mirror: Mirror::default(),
+            token: None,
+        }
+    }
+
+    /// Authenticate api requests, raising GitHub's rate limit
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token.filter(|token| !token.is_empty());
+        self
+    }
+
+    /// Send requests and downloads to a mirror instead
+    pub fn with_mirror(mut self, mirror: Mirror) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    /// Never make requests, only cached responses are used
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    #[inline]
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Set how long cached responses are used before they are revalidated
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.cache.set_ttl(ttl);
+        self
+    }
+
+    /// Base url of the releases api
+    fn api(&self) -> &str {
+        self.mirror
+            .api
+            .as_deref()
+            .unwrap_or(Self::GITHUB_API)
+            .trim_end_matches('/')
+    }
+
+    /// Base url of the npm registry
+    pub fn registry(&self) -> &str {
+        self.mirror
+            .registry
+            .as_deref()
+            .unwrap_or(Self::NPM_REGISTRY)
+            .trim_end_matches('/')
+    }
+
+    /// Url that is requested for `url` once mirrors are applied
+    fn mirrored(&self, url: &str) -> String {
+        for (origin, mirror) in [
+            (Self::GITHUB, &self.mirror.downloads),
+            (Self::NPM_REGISTRY, &self.mirror.registry),
+        ] {
+            if let (Some(mirror), Some(path)) = (mirror, url.strip_prefix(origin)) {
+                return format!("{}{path}", mirror.trim_end_matches('/'));
+            }
+        }
+        url.to_string()
+    }
+
+    /// Download a release asset or package to `dest`
+    ///
+    /// The download is streamed to `<dest>.part` and only moved to `dest` once it's complete,
+    /// so `dest` never holds part of a download. Dropped connections are retried with backoff
+    /// and continue where they stopped, as does a download that was interrupted before.
+    ///
+    /// `file://` urls are read from disk so a mirror can be a plain directory
+    pub async fn download(
+        &self,
+        url: &str,
+        dest: &Path,
+        spinner: &mut Progress,
+    ) -> anyhow::Result<()> {
+        let url = self.mirrored(url);
+        let name = dest
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let part = dest.with_file_name(format!("{name}.part"));
+
+        if let Some(path) = url.strip_prefix("file://") {
+            std::fs::copy(path, &part)
+                .map_err(|e| anyhow::anyhow!("failed to read {path}: {e}"))?;
+            std::fs::rename(&part, dest)?;
+            return Ok(());
         }
+        if self.offline {
+            return Err(anyhow::anyhow!("{url} can't be downloaded while offline"));
+        }
+
+        let mut attempt = 1;
+        while let Err(err) = self.download_part(&url, &part, &name, spinner).await {
+            if attempt == Self::DOWNLOAD_ATTEMPTS || !is_retryable(&err) {
+                return Err(err);
+            }
+
+            let delay = Duration::from_secs(1 << (attempt - 1));
+            spinner.log(format!(
+                " └ downloading {name} failed, retrying in {}s: {err}",
+                delay.as_secs()
+            ));
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+
+        std::fs::rename(&part, dest)?;
+        Ok(())
     }
 
-    fn get(&self, url: impl IntoUrl, params: Option<BTreeMap<String, String>>) -> RequestBuilder {
-        let url = if let Some(params) = params {
-            format!(
-                "{}?{}",
-                url.into_url().unwrap(),
-                params
-                    .iter()
-                    .map(|(k, v)| format!("{k}={v}"))
-                    .collect::<Vec<_>>()
-                    .join(","),
-            )
+    /// Download the rest of `url` to `part`, or all of it when the server can't resume it
+    async fn download_part(
+        &self,
+        url: &str,
+        part: &Path,
+        name: &str,
+        spinner: &mut Progress,
+    ) -> anyhow::Result<()> {
+        let request = |offset: u64| {
+            // A stalled connection is treated like a dropped one so it's retried
+            let client = reqwest::Client::builder()
+                .read_timeout(Self::DOWNLOAD_TIMEOUT)
+                .build()?;
+            let mut request = client.get(url).header("User-Agent", &self.user_agent);
+            // Artifacts of workflow runs are downloaded through the api
+            if let Some(token) = self.token.as_ref().filter(|_| url.starts_with(self.api())) {
+                request = request.bearer_auth(token);
+            }
+            anyhow::Ok(match offset {
+                0 => request,
+                offset => request.header(header::RANGE, format!("bytes={offset}-")),
+            })
+        };
+
+        let offset = part.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        let mut response = request(offset)?.send().await?;
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            // The partial download doesn't belong to what is served now, e.g. it was replaced
+            response = request(0)?.send().await?;
+        }
+        let mut response = response.error_for_status()?;
+
+        let resumed = response.status() == StatusCode::PARTIAL_CONTENT;
+        let (mut file, mut done) = if resumed {
+            (OpenOptions::new().append(true).open(part)?, offset)
         } else {
-            url.into_url().unwrap().to_string()
+            (File::create(part)?, 0)
         };
+        let total = response.content_length().map(|length| length + done);
+
+        let started = Instant::now();
+        let mut shown = started;
+        let mut received = 0;
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk)?;
+            done += chunk.len() as u64;
+            received += chunk.len() as u64;
 
-        reqwest::Client::new()
+            if shown.elapsed() >= Duration::from_millis(100) {
+                shown = Instant::now();
+                let rate = received as f64 / started.elapsed().as_secs_f64();
+                spinner.bytes(format!("downloading {name}"), done, total, rate as u64);
+            }
+        }
+
+        file.flush()?;
+        Ok(())
+    }
+
+    fn get(&self, url: &str) -> RequestBuilder {
+        let mut request = reqwest::Client::new()
             .get(url)
             .header("User-Agent", &self.user_agent)
             .header("X-GITHUB-API-VERSION", "2022-11-28")
-            .header("Accept", "application/vnd.github+json")
+            .header("Accept", "application/vnd.github+json");
+
+        // The token is only ever sent to the api it belongs to
+        if let Some(token) = self.token.as_ref().filter(|_| url.starts_with(self.api())) {
+            request = request.bearer_auth(token);
+        }
+
+        request
     }
 
+    /// Get a response, using the cache when possible
+    ///
+    /// Returns `None` when nothing exists at the url. A stale cached response is used when the
+    /// request fails, e.g. when rate limited or offline.
+    async fn fetch(&self, url: &str) -> anyhow::Result<Option<Entry>> {
+        let url = self.mirrored(url);
+        if let Some(path) = url.strip_prefix("file://") {
+            // Static mirrors can't serve query parameters, e.g. `per_page`
+            let path = path.split('?').next().unwrap_or(path);
+            return match std::fs::read_to_string(path) {
+                Ok(body) => Ok(Some(Entry::new(url.as_str(), None, None, body))),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::NotFound | std::io::ErrorKind::NotADirectory
+                    ) =>
+                {
+                    Ok(None)
+                }
+                Err(e) => Err(anyhow::anyhow!("failed to read {path}: {e}")),
+            };
+        }
+
+        let cached = self.cache.get(&url);
+        if cached
+            .as_ref()
+            .is_some_and(|entry| entry.is_fresh(self.cache.ttl()))
+        {
+            return Ok(cached);
+        }
+
+        if self.offline {
+            return match cached {
+                Some(entry) => Ok(Some(entry)),
+                None => Err(anyhow::anyhow!("{url} can't be requested while offline")),
+            };
+        }
+
+        let mut request = self.get(&url);
+        if let Some(etag) = cached.as_ref().and_then(|entry| entry.etag.as_ref()) {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+
+        let response = match (request.send().await, cached) {
+            (Ok(response), cached) => (response, cached),
+            (Err(_), Some(entry)) => return Ok(Some(entry)),
+            (Err(err), None) => return Err(err.into()),
+        };
+
+        let status = response.0.status();
+        let entry = match response {
+            (_, Some(mut entry)) if status == StatusCode::NOT_MODIFIED => {
+                entry.touch();
+                entry
+            }
+            (response, _) if status.is_success() => {
+                let header = |name| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|v| v.to_str().ok())
+                        .map(str::to_string)
+                };
+                let etag = header(header::ETAG);
+                let next = header(header::LINK).and_then(|link| next_page(&link));
+                Entry::new(url, etag, next, response.text().await?)
+            }
+            (_, _) if status == StatusCode::NOT_FOUND => return Ok(None),
+            (_, Some(entry)) => return Ok(Some(entry)),
+            (_, None) => return Err(anyhow::anyhow!("request to {url} failed: {status}")),
+        };
+
+        // Failing to cache only costs another request next time
+        let _ = self.cache.put(&entry);
+        Ok(Some(entry))
+    }
+
+    /// Get a json response, using the cache when possible
+    pub(crate) async fn get_cached<T: DeserializeOwned>(&self, url: String) -> anyhow::Result<T> {
+        let entry = self
+            .fetch(&url)
+            .await?
+            .ok_or(anyhow::anyhow!("{url} was not found"))?;
+        Ok(serde_json::from_str(&entry.body)?)
+    }
+
+    /// Every release of the repo, following the pages of the listing
     pub async fn releases(
         &self,
         owner: impl std::fmt::Display,
         repo: impl std::fmt::Display,
     ) -> anyhow::Result<Vec<Release>> {
+        let mut page = Some(
+            Url::parse_with_params(
+                &format!("{}/repos/{owner}/{repo}/releases", self.api()),
+                [("per_page", "100")],
+            )?
+            .to_string(),
+        );
+
+        let mut releases = Vec::new();
+        while let Some(url) = page {
+            let entry = self
+                .fetch(&url)
+                .await?
+                .ok_or(anyhow::anyhow!("no releases found for {owner}/{repo}"))?;
+            // Releases tagged with something that isn't a version, like `nightly`, are skipped
+            // rather than failing the whole listing
+            releases.extend(
+                serde_json::from_str::<Vec<serde_json::Value>>(&entry.body)?
+                    .into_iter()
+                    .filter_map(|release| serde_json::from_value::<Release>(release).ok()),
+            );
+            page = entry.next;
+        }
+
+        Ok(releases)
+    }
+
+    /// Release of the repo for the version
+    ///
+    /// The release is requested by it's tag, with and without a `v` prefix, so it only takes a
+    /// single request. Every release is searched when neither tag exists, or when offline and
+    /// the tag was never requested.
+    pub async fn release(
+        &self,
+        owner: impl std::fmt::Display,
+        repo: impl std::fmt::Display,
+        version: &Version,
+    ) -> anyhow::Result<Option<Release>> {
+        for tag in [version.to_string(), format!("v{version}")] {
+            let url = format!("{}/repos/{owner}/{repo}/releases/tags/{tag}", self.api());
+            match self.fetch(&url).await {
+                Ok(Some(entry)) => return Ok(Some(serde_json::from_str(&entry.body)?)),
+                Ok(None) => {}
+                // A listing cached for another version can still have the release
+                Err(_) if self.offline => {}
+                Err(err) => return Err(err),
+            }
+        }
+
         Ok(self
-            .get(
-                format!("{}/repos/{owner}/{repo}/releases", Self::GITHUB_API),
-                None,
-            )
-            .send()
+            .releases(owner, repo)
             .await?
-            .json()
-            .await?)
+            .into_iter()
+            .find(|release| release.tag == *version))
     }
+
+    /// Successful workflow runs of the repo's branch, newest first
+    pub async fn runs(
+        &self,
+        owner: impl std::fmt::Display,
+        repo: impl std::fmt::Display,
+        branch: &str,
+    ) -> anyhow::Result<Vec<Run>> {
+        let url = Url::parse_with_params(
+            &format!("{}/repos/{owner}/{repo}/actions/runs", self.api()),
+            [
+                ("branch", branch),
+                ("status", "success"),
+                ("per_page", "20"),
+            ],
+        )?
+        .to_string();
+
+        let entry = self
+            .fetch(&url)
+            .await?
+            .ok_or(anyhow::anyhow!("no workflow runs found for {owner}/{repo}"))?;
+        Ok(serde_json::from_str::<actions::Runs>(&entry.body)?.workflow_runs)
+    }
+
+    /// Artifacts uploaded by a workflow run of the repo
+    pub async fn artifacts(
+        &self,
+        owner: impl std::fmt::Display,
+        repo: impl std::fmt::Display,
+        run: u64,
+    ) -> anyhow::Result<Vec<Artifact>> {
+        let url = Url::parse_with_params(
+            &format!(
+                "{}/repos/{owner}/{repo}/actions/runs/{run}/artifacts",
+                self.api()
+            ),
+            [("per_page", "100")],
+        )?
+        .to_string();
+
+        let entry = self.fetch(&url).await?.ok_or(anyhow::anyhow!(
+            "workflow run {run} of {owner}/{repo} was not found"
+        ))?;
+        Ok(serde_json::from_str::<actions::Artifacts>(&entry.body)?.artifacts)
+    }
+
+    /// Url an artifact of a workflow run is downloaded from
+    ///
+    /// GitHub only serves artifacts to authenticated requests, so without a token they are
+    /// downloaded from the `nightly` mirror, e.g. `https://nightly.link`.
+    pub fn artifact_url(
+        &self,
+        owner: impl std::fmt::Display,
+        repo: impl std::fmt::Display,
+        run: u64,
+        artifact: &Artifact,
+    ) -> anyhow::Result<String> {
+        match (&self.mirror.nightly, &self.token) {
+            (Some(nightly), _) => Ok(format!(
+                "{}/{owner}/{repo}/actions/runs/{run}/{}.zip",
+                nightly.trim_end_matches('/'),
+                artifact.name
+            )),
+            (None, Some(_)) => Ok(artifact.archive_download_url.clone()),
+            (None, None) => Err(anyhow::anyhow!(
+                "downloading {} from GitHub needs a token, set GITHUB_TOKEN or the `nightly` mirror",
+                artifact.name
+            )),
+        }
+    }
+}
+
+/// Whether a download failed in a way that can succeed when tried again
+///
+/// Dropped connections, server errors, and rate limits are retried, but missing downloads and
+/// failing to write to disk are not.
+fn is_retryable(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<reqwest::Error>().is_some_and(|err| {
+        err.status().is_none_or(|status| {
+            status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+        })
+    })
+}
+
+/// Url of the next page from a `Link` header
+///
+/// e.g. `<https://api.github.com/...?page=2>; rel="next", <...?page=5>; rel="last"`
+fn next_page(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let mut params = part.split(';').map(str::trim);
+        let url = params.next()?.strip_prefix('<')?.strip_suffix('>')?;
+        params
+            .any(|param| param == "rel=\"next\"")
+            .then(|| url.to_string())
+    })
 }
@@ -1,9 +1,19 @@
-use std::{fs::File, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use regex::Regex;
 use serde::Deserialize;
 
-use crate::{Progress, SpinnerError, Version, DATA};
+use super::Client;
+use crate::{
+    checksum,
+    config::{Framework, Target},
+    extract,
+    index::{Index, Install},
+    Progress, SpinnerError, Version, DATA,
+};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Asset {
@@ -65,196 +75,239 @@ pub struct Release {
 }
 
 impl Release {
-    pub fn get_platform_asset(&self) -> Option<&Asset> {
-        self.assets.iter().find(|v| {
-            #[cfg(target_os = "windows")]
-            {
-                v.name.ty.is_win_64()
-            }
-            #[cfg(target_os = "macos")]
-            {
-                v.name.ty.is_macos()
-            }
-            #[cfg(target_os = "linux")]
-            {
-                v.name.ty.is_linux()
-            }
-            #[cfg(target_os = "android")]
-            {
-                v.name.ty.is_android()
-            }
-            #[cfg(target_os = "ios")]
-            {
-                v.name.ty.is_ios()
-            }
+    /// Asset of the release that is installed for the target
+    ///
+    /// Android games are built from the `-embed` apk, which is used over the plain one.
+    pub fn get_platform_asset(&self, target: Target) -> Option<&Asset> {
+        let mut assets = self.assets.iter().filter(|v| v.name.ty.is_for(target));
+        match target {
+            Target::Android => assets.max_by_key(|v| v.name.embed),
+            _ => assets.next(),
+        }
+    }
+
+    /// Where the release's asset for the target is downloaded to
+    pub fn archive(&self, target: Target) -> Option<PathBuf> {
+        self.get_platform_asset(target).map(|asset| {
+            DATA.join(target.to_string())
+                .join(".archive")
+                .join(&asset.name.name)
         })
     }
 
+    /// Download and unpack the release's asset to `DATA/<target>/<framework>/<version>`
+    ///
+    /// Any target can be installed from any host so builds for every target can be made
+    /// from one machine. Versions are installed side by side and recorded in the [`Index`],
+    /// so an installed version is never downloaded or unpacked again.
     pub async fn install(
         &self,
-        base_name: impl AsRef<str>,
+        client: &Client,
+        framework: Framework,
+        target: Target,
         spinner: &mut Progress,
     ) -> anyhow::Result<()> {
-        match self.get_platform_asset() {
-            Some(asset) => {
-                let base = DATA.join(std::env::consts::OS);
-                let mut work_done = false;
-
-                let name = base_name.as_ref();
-                let zip_name = asset.name.name.clone();
-
-                let archive_path = base.join(".archive");
-                let version_file = format!(".{name}-version");
-                let zip_file = archive_path.join(&zip_name);
-
-                if !archive_path.exists() {
-                    std::fs::create_dir_all(&archive_path).log_err_in_spin(
-                        spinner,
-                        format!("failed to create directory {}", archive_path.display()),
-                    )?;
-                }
+        let asset = self.get_platform_asset(target).ok_or(anyhow::anyhow!(
+            "no {framework} download for target: {target}"
+        ))?;
+
+        Download {
+            version: &self.tag,
+            asset: &asset.name.name,
+            url: &asset.browser_download_url,
+            // GitHub lists a size of 0 for assets that are still uploading
+            size: (asset.size > 0).then_some(asset.size as u64),
+            sha256: None,
+        }
+        .install(client, framework, target, spinner)
+        .await
+    }
+}
 
-                if !zip_file.exists() {
-                    work_done = true;
-                    spinner.update(format!(
-                        "installing `{}` for {}",
-                        base_name.as_ref(),
-                        std::env::consts::OS
-                    ));
-                    let response = reqwest::get(asset.browser_download_url.as_str())
-                        .await
-                        .log_err_in_spin(spinner, "failed to download release")?;
-
-                    let content = response
-                        .bytes()
-                        .await
-                        .log_err_in_spin(spinner, "failed to read download as bytes")?;
-
-                    std::fs::write(&zip_file, &content)
-                        .log_err_in_spin(spinner, "failed to write download to disk")?;
-                }
+/// Asset of a release that is installed as a version of a framework
+pub struct Download<'a> {
+    pub version: &'a Version,
+    /// Name of the asset, which determines how it is unpacked
+    pub asset: &'a str,
+    pub url: &'a str,
+    /// Size listed for the asset
+    pub size: Option<u64>,
+    /// Checksum the asset is pinned to
+    pub sha256: Option<&'a str>,
+}
 
-                if base.join(&version_file).exists() {
-                    let version = Version::from_str(
-                        std::fs::read_to_string(base.join(&version_file))
-                            .log_err_in_spin(
-                                spinner,
-                                format!(
-                                    "failed to read file {}",
-                                    base.join(&version_file).display()
-                                ),
-                            )?
-                            .trim(),
-                    )
-                    .map_err(|e| anyhow::anyhow!("{}", e))?;
-                    if version == self.tag {
-                        if work_done {
-                            spinner.success(format!("Installed {} {}", name, self.tag).as_str());
-                        }
-                        return Ok(());
-                    } else {
-                        std::fs::write(base.join(&version_file), self.tag.to_string())
-                            .log_err_in_spin(spinner, "failed to save installed version")?;
-                    }
-                } else {
-                    std::fs::write(base.join(&version_file), self.tag.to_string())
-                        .log_err_in_spin(spinner, "failed to save installed version")?;
-                }
+impl Download<'_> {
+    /// Download and unpack the asset to `DATA/<target>/<framework>/<version>`
+    ///
+    /// Downloads are checked against the asset's size and `sha256` when it is pinned. A
+    /// previous download that no longer matches, e.g. because it was truncated, is downloaded
+    /// again.
+    pub async fn install(
+        &self,
+        client: &Client,
+        framework: Framework,
+        target: Target,
+        spinner: &mut Progress,
+    ) -> anyhow::Result<()> {
+        let mut index = Index::load()?;
+        if index
+            .get(framework, target, self.version)
+            .is_some_and(|install| install.matches(self.sha256))
+        {
+            return Ok(());
+        }
 
-                if zip_name.ends_with(".zip") {
-                    work_done = true;
-                    spinner.update(format!(
-                        "unzipping `{}` for {}",
-                        base_name.as_ref(),
-                        std::env::consts::OS
-                    ));
-
-                    let zf = File::open(&zip_file)?;
-                    let mut archive = zip::ZipArchive::new(&zf)?;
-                    let base = base.join(name);
-                    if !base.exists() {
-                        std::fs::create_dir_all(&base)?;
-                    } else {
-                        std::fs::remove_dir_all(&base)?;
-                        std::fs::create_dir_all(&base)?;
-                    }
-
-                    for i in 0..archive.len() {
-                        // Get the file at the current index.
-                        let mut file = archive.by_index(i)?;
-                        // Get the path to extract the file to.
-                        let outpath = match file.enclosed_name() {
-                            Some(path) => path.to_owned(),
-                            None => continue, // Skip to the next file if the path is None.
-                        };
-
-                        if file.name().ends_with('/') {
-                            std::fs::create_dir_all(base.join(outpath.file_name().unwrap()))
-                                .log_err_in_spin(
-                                    spinner,
-                                    format!(
-                                        "failed to create directory {}",
-                                        base.join(outpath.file_name().unwrap()).display()
-                                    ),
-                                )?; // Create the directory.
-                        } else {
-                            spinner.log(format!(" └ unzipped file {}", outpath.display()));
-
-                            // Create and copy the file contents to the output path.
-                            let mut outfile = File::create(base.join(outpath.file_name().unwrap()))
-                                .log_err_in_spin(
-                                    spinner,
-                                    format!(
-                                        "failed to create file {}",
-                                        base.join(outpath.file_name().unwrap()).display()
-                                    ),
-                                )?;
-
-                            std::io::copy(&mut file, &mut outfile).log_err_in_spin(
-                                spinner,
-                                format!("failed to unzip file {}", outpath.display()),
-                            )?;
-                        }
-
-                        // Set file permissions if running on a Unix-like system.
-                        #[cfg(unix)]
-                        {
-                            use std::os::unix::fs::PermissionsExt;
-
-                            if let Some(mode) = file.unix_mode() {
-                                std::fs::set_permissions(
-                                    &outpath,
-                                    std::fs::Permissions::from_mode(mode),
-                                )
-                                .ok_or_spin(spinner, "failed to copy file permissions");
-                            }
-                        }
-                    }
-                } else if zip_name.ends_with(".AppImage") {
-                    work_done = true;
-                    std::fs::rename(
-                        &zip_file,
-                        zip_file.with_file_name(format!("{}.AppImage", name)),
-                    )
-                    .log_err_in_spin(spinner, "failed to rename AppImage")?;
-                }
+        let name = framework.to_string();
+        let zip_name = self.asset.to_string();
+        let ty = AssetName::from_str(self.asset)
+            .map_err(|e| anyhow::anyhow!("{e}"))?
+            .ty;
+
+        let archive_path = DATA.join(target.to_string()).join(".archive");
+        let zip_file = archive_path.join(self.asset);
+        let archive_path = archive_path.as_path();
+
+        if !archive_path.exists() {
+            std::fs::create_dir_all(archive_path).log_err_in_spin(
+                spinner,
+                format!("failed to create directory {}", archive_path.display()),
+            )?;
+        }
 
-                if work_done {
-                    spinner.success(format!("Installed {} {}", name, self.tag).as_str());
+        let size = self.size;
+        let expected = self.sha256.or(index.checksum(target, &zip_name));
+
+        let mut checksum = None;
+        if zip_file.exists() {
+            match checksum::verify_file(&zip_file, size, expected) {
+                Ok(actual) => checksum = Some(actual),
+                Err(err) => {
+                    spinner.log(format!(" └ {err}, downloading it again"));
+                    std::fs::remove_file(&zip_file)?;
                 }
             }
+        }
+
+        let checksum = match checksum {
+            Some(checksum) => checksum,
             None => {
-                return Err(anyhow::anyhow!(
-                    "no download for current target os: {}",
-                    std::env::consts::OS
-                ))
+                spinner.update(format!("installing `{name}` {} for {target}", self.version));
+                client
+                    .download(self.url, &zip_file, spinner)
+                    .await
+                    .log_err_in_spin(spinner, "failed to download release")?;
+
+                // Removed so it's downloaded again instead of failing every install
+                let actual = checksum::verify_file(&zip_file, size, expected);
+                if actual.is_err() {
+                    std::fs::remove_file(&zip_file)?;
+                }
+                actual.log_err_in_spin(spinner, "downloaded release is corrupt")?
+            }
+        };
+
+        // Only this version is replaced, other installed versions are left alone
+        let base = framework.path(target, self.version);
+        if base.exists() {
+            std::fs::remove_dir_all(&base)?;
+        }
+        std::fs::create_dir_all(&base).log_err_in_spin(
+            spinner,
+            format!("failed to create directory {}", base.display()),
+        )?;
+
+        if ty.is_macos() {
+            // The app bundle is rebuilt straight from the zip so it's symlinks and
+            // permissions stay intact, even when it's installed on another os
+            std::fs::copy(&zip_file, base.join(format!("{name}.app.zip")))
+                .log_err_in_spin(spinner, "failed to copy app bundle")?;
+        } else if zip_name.ends_with(".zip") {
+            spinner.update(format!("unzipping `{name}` {} for {target}", self.version));
+
+            let files = extract::unzip(&zip_file, &base)
+                .log_err_in_spin(spinner, format!("failed to unzip {zip_name}"))?;
+            for file in files {
+                spinner.log(format!(" └ unzipped file {}", file.display()));
+            }
+            unwrap(&base, framework, target)
+                .log_err_in_spin(spinner, format!("failed to unpack {zip_name}"))?;
+        } else if let Some(ext @ ("AppImage" | "apk")) = zip_name.rsplit('.').next() {
+            // Keep the download in the archive so it isn't downloaded again
+            let installed = base.join(format!("{name}.{ext}"));
+            std::fs::copy(&zip_file, &installed)
+                .log_err_in_spin(spinner, format!("failed to copy {ext}"))?;
+
+            #[cfg(unix)]
+            if ext == "AppImage" {
+                use std::os::unix::fs::PermissionsExt;
+
+                std::fs::set_permissions(&installed, std::fs::Permissions::from_mode(0o755))
+                    .ok_or_spin(spinner, "failed to make AppImage executable");
             }
         }
 
+        index.insert(Install {
+            framework,
+            target,
+            version: self.version.clone(),
+            asset: zip_name,
+            url: Some(self.url.to_string()),
+            sha256: Some(checksum),
+        });
+        index
+            .save()
+            .log_err_in_spin(spinner, "failed to save install index")?;
+
+        spinner.success(format!("Installed {name} {}", self.version));
         Ok(())
     }
 }
 
+/// Install a build that came wrapped in another zip, like workflow artifacts do
+///
+/// The AppImage, apk, or app bundle's zip is renamed to what it's installed as, and a zip of
+/// the windows build is unzipped in place.
+fn unwrap(base: &Path, framework: Framework, target: Target) -> anyhow::Result<()> {
+    let files = std::fs::read_dir(base)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    let find = |ext: &str| {
+        files
+            .iter()
+            .find(|path| path.to_string_lossy().ends_with(ext))
+    };
+
+    let (wrapped, installed) = match target {
+        Target::Linux => (find(".AppImage"), format!("{framework}.AppImage")),
+        Target::Android => (find(".apk"), format!("{framework}.apk")),
+        Target::Macos => (find(".zip"), format!("{framework}.app.zip")),
+        Target::Win64 if files.len() == 1 => {
+            let Some(zip) = find(".zip") else {
+                return Ok(());
+            };
+            extract::unzip(zip, base)?;
+            std::fs::remove_file(zip)?;
+            return Ok(());
+        }
+        _ => return Ok(()),
+    };
+
+    let installed = base.join(installed);
+    if let (Some(wrapped), false) = (wrapped, installed.exists()) {
+        std::fs::rename(wrapped, &installed)?;
+
+        #[cfg(unix)]
+        if target == Target::Linux {
+            use std::os::unix::fs::PermissionsExt;
+
+            std::fs::set_permissions(&installed, std::fs::Permissions::from_mode(0o755))?;
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, strum::EnumIs)]
 pub enum AssetType {
     Android,
@@ -265,10 +318,27 @@ pub enum AssetType {
     Other,
 }
 
+impl AssetType {
+    /// Whether the asset is the framework's runtime for the target
+    pub fn is_for(&self, target: Target) -> bool {
+        match target {
+            Target::Win64 => self.is_win_64(),
+            Target::Macos => self.is_macos(),
+            Target::Linux => self.is_linux(),
+            Target::Ios => self.is_ios(),
+            Target::Android => self.is_android(),
+            // love.js is published separately from the framework
+            Target::Web => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AssetName {
     name: String,
     ty: AssetType,
+    /// Build meant to have a game embedded into it, e.g. `love-11.5-android-embed.apk`
+    embed: bool,
 }
 
 impl<'de> Deserialize<'de> for AssetName {
@@ -286,9 +356,10 @@ impl FromStr for AssetName {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let targeted = Regex::new(r"(love|lovr)-(v?\d+(?:\.\d+)*)[-.](?<os>android|ios|macos|win64|x86_64|apk|app)(?:.apk|.zip|.AppImage)").unwrap();
+        let targeted = Regex::new(r"(love|lovr)-(v?\d+(?:\.\d+)*)[-.](?<os>android|ios|macos|win64|x86_64|apk|app)(?<embed>-embed)?(?:.apk|.zip|.AppImage)").unwrap();
 
-        let asset_type = match targeted.captures(s) {
+        let captures = targeted.captures(s);
+        let asset_type = match &captures {
             Some(captures) => match captures.name("os").as_ref().map(|v| v.as_str()) {
                 Some("android" | "apk") => AssetType::Android,
                 Some("ios") => AssetType::Ios,
@@ -304,6 +375,7 @@ impl FromStr for AssetName {
         Ok(Self {
             name: s.to_string(),
             ty: asset_type,
+            embed: captures.is_some_and(|captures| captures.name("embed").is_some()),
         })
     }
 }
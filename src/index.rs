@@ -0,0 +1,219 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Framework, Target},
+    lock::Locked,
+    Version, DATA,
+};
+
+/// Index of every framework version installed in `DATA`
+///
+/// Stored as `DATA/index.toml`. An install is only recorded once it has been fully unpacked
+/// so an interrupted install is never mistaken for a finished one.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Index {
+    /// Root directories of the projects lbt has been used in
+    ///
+    /// Used to find which installs are still referenced when pruning
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub projects: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub installed: Vec<Install>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Install {
+    pub framework: Framework,
+    pub target: Target,
+    pub version: Version,
+    /// Name of the downloaded asset the install was unpacked from
+    pub asset: String,
+    /// Where the asset was downloaded from, without mirrors applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// SHA-256 of the downloaded asset as hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+impl Install {
+    /// Directory the install was unpacked to
+    #[inline]
+    pub fn path(&self) -> PathBuf {
+        self.framework.path(self.target, &self.version)
+    }
+
+    /// Downloaded asset the install was unpacked from
+    #[inline]
+    pub fn archive(&self) -> PathBuf {
+        DATA.join(self.target.to_string())
+            .join(".archive")
+            .join(&self.asset)
+    }
+
+    /// Size of the install on disk in bytes
+    pub fn size(&self) -> u64 {
+        dir_size(&self.path())
+    }
+
+    /// Whether the install was unpacked from the download pinned by `sha256`, if any
+    pub fn matches(&self, sha256: Option<&str>) -> bool {
+        sha256.is_none_or(|sha256| {
+            self.sha256
+                .as_deref()
+                .is_some_and(|actual| actual.eq_ignore_ascii_case(sha256))
+        })
+    }
+
+    /// What the install is locked to, unless it was installed before downloads were recorded
+    pub fn locked(&self) -> Option<Locked> {
+        Some(Locked {
+            framework: self.framework,
+            target: self.target,
+            version: self.version.clone(),
+            asset: self.asset.clone(),
+            url: self.url.clone()?,
+            sha256: self.sha256.clone()?,
+        })
+    }
+
+    #[inline]
+    fn is(&self, framework: Framework, target: Target, version: &Version) -> bool {
+        self.framework == framework && self.target == target && &self.version == version
+    }
+}
+
+impl Index {
+    #[inline]
+    pub fn path() -> PathBuf {
+        DATA.join("index.toml")
+    }
+
+    pub fn load() -> anyhow::Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        toml::from_str(&std::fs::read_to_string(&path)?)
+            .map_err(|e| anyhow::anyhow!("invalid install index {}: {e}", path.display()))
+    }
+
+    /// Write the index, replacing the previous one in a single step
+    pub fn save(&self) -> anyhow::Result<()> {
+        std::fs::create_dir_all(DATA.as_path())?;
+
+        let tmp = Self::path().with_extension("toml.tmp");
+        std::fs::write(&tmp, toml::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp, Self::path())?;
+        Ok(())
+    }
+
+    /// Installed version of the framework for the target
+    ///
+    /// Installs whose directory was removed from `DATA` are treated as not installed
+    pub fn get(&self, framework: Framework, target: Target, version: &Version) -> Option<&Install> {
+        self.installed
+            .iter()
+            .find(|install| install.is(framework, target, version))
+            .filter(|install| install.path().exists())
+    }
+
+    /// Recorded checksum of a downloaded asset for the target
+    pub fn checksum(&self, target: Target, asset: &str) -> Option<&str> {
+        self.downloaded(target, asset)
+            .find_map(|install| install.sha256.as_deref())
+    }
+
+    /// Recorded url a downloaded asset for the target came from
+    pub fn url(&self, target: Target, asset: &str) -> Option<&str> {
+        self.downloaded(target, asset)
+            .find_map(|install| install.url.as_deref())
+    }
+
+    fn downloaded(&self, target: Target, asset: &str) -> impl Iterator<Item = &Install> {
+        let asset = asset.to_string();
+        self.installed
+            .iter()
+            .filter(move |install| install.target == target && install.asset == asset)
+    }
+
+    /// Record an install, replacing any previous record of the same version
+    pub fn insert(&mut self, install: Install) {
+        self.remove(install.framework, install.target, &install.version);
+        self.installed.push(install);
+        self.installed.sort_by(|a, b| {
+            (a.framework, a.target, &a.version).cmp(&(b.framework, b.target, &b.version))
+        });
+    }
+
+    /// Record a project so the versions it uses are kept when pruning
+    ///
+    /// Returns whether the project wasn't known yet
+    pub fn add_project(&mut self, root: &Path) -> bool {
+        if self.projects.iter().any(|project| project == root) {
+            return false;
+        }
+
+        self.projects.push(root.to_path_buf());
+        self.projects.sort();
+        true
+    }
+
+    /// Delete an install from disk and remove it from the index
+    ///
+    /// The downloaded asset is deleted as well unless another install was unpacked from it
+    pub fn uninstall(
+        &mut self,
+        framework: Framework,
+        target: Target,
+        version: &Version,
+    ) -> anyhow::Result<Option<Install>> {
+        let Some(install) = self.remove(framework, target, version) else {
+            return Ok(None);
+        };
+
+        if install.path().exists() {
+            std::fs::remove_dir_all(install.path())?;
+        }
+        let shared = self
+            .installed
+            .iter()
+            .any(|other| other.archive() == install.archive());
+        if !shared && install.archive().exists() {
+            std::fs::remove_file(install.archive())?;
+        }
+
+        Ok(Some(install))
+    }
+
+    pub fn remove(
+        &mut self,
+        framework: Framework,
+        target: Target,
+        version: &Version,
+    ) -> Option<Install> {
+        let i = self
+            .installed
+            .iter()
+            .position(|install| install.is(framework, target, version))?;
+        Some(self.installed.remove(i))
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(metadata) = path.symlink_metadata() else {
+        return 0;
+    };
+
+    if metadata.is_dir() {
+        std::fs::read_dir(path)
+            .map(|entries| entries.flatten().map(|entry| dir_size(&entry.path())).sum())
+            .unwrap_or(0)
+    } else {
+        metadata.len()
+    }
+}
@@ -2,26 +2,33 @@ use std::path::PathBuf;
 
 use spinoff::{spinners, Color, Spinner};
 
+mod checksum;
+mod extract;
 mod progress;
+mod requirement;
 mod version;
 
 pub mod build;
 pub mod config;
 pub mod git;
+pub mod index;
+pub mod lock;
+pub mod server;
 
-pub use progress::Progress;
+pub use progress::{size, Progress};
+pub use requirement::Requirement;
 pub use version::Version;
 
 lazy_static::lazy_static! {
     pub static ref DATA: PathBuf = dirs::data_local_dir().unwrap().join("love-build-tools");
 }
 
-pub fn love_path() -> PathBuf {
-    DATA.join(std::env::consts::OS).join("love")
+pub fn love_path(version: &Version) -> PathBuf {
+    config::Framework::Love.path(config::Target::default(), version)
 }
 
-pub fn lovr_path() -> PathBuf {
-    DATA.join(std::env::consts::OS).join("lovr")
+pub fn lovr_path(version: &Version) -> PathBuf {
+    config::Framework::Lovr.path(config::Target::default(), version)
 }
 
 pub trait SpinnerPrint {
@@ -0,0 +1,84 @@
+-- Written by `lbt run --live` in place of the game's main.lua, which is moved to {{main}}
+--
+-- Connects to lbt and reloads the modules it says changed while the game keeps running.
+-- Tables of reloaded modules are updated in place, so anything holding on to them sees the
+-- new functions and the game's state is kept.
+local socket = require("socket")
+
+local client = socket.tcp()
+client:settimeout(1)
+if client:connect("127.0.0.1", {{port}}) then
+    client:settimeout(0)
+else
+    print("[lbt] failed to connect, modules won't be reloaded")
+    client = nil
+end
+
+local function reply(...)
+    if client then
+        client:send(table.concat({ ... }, "\t"):gsub("\n", " ") .. "\n")
+    end
+end
+
+-- Modules are read from disk since the game is run from a package made when it started
+local function reload(name, path)
+    if package.loaded[name] == nil then
+        return reply("skipped", name)
+    end
+
+    local chunk, err = loadfile(path)
+    if not chunk then
+        return reply("failed", name, err)
+    end
+    local ok, result = pcall(chunk, name)
+    if not ok then
+        return reply("failed", name, result)
+    end
+    if result == nil then
+        result = true
+    end
+
+    local old = package.loaded[name]
+    if type(old) == "table" and type(result) == "table" then
+        for key, value in pairs(result) do
+            old[key] = value
+        end
+    else
+        package.loaded[name] = result
+    end
+
+    if love.reload then
+        love.reload(name)
+    end
+    reply("reloaded", name)
+end
+
+local buffer = ""
+local function poll()
+    while client do
+        local line, err, partial = client:receive("*l", buffer)
+        if line then
+            buffer = ""
+            local command, name, path = line:match("^(%w+)\t([^\t]+)\t(.+)$")
+            if command == "reload" then
+                reload(name, path)
+            end
+        elseif err == "timeout" then
+            buffer = partial or buffer
+            return
+        else
+            client = nil
+        end
+    end
+end
+
+-- Polled every frame, from love.run or a game's own loop
+if love.event then
+    local pump = love.event.pump
+    love.event.pump = function(...)
+        poll()
+        return pump(...)
+    end
+end
+
+love.filesystem.load("{{main}}")()
@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Framework, Target},
+    Version,
+};
+
+/// Downloads the project's frameworks are locked to
+///
+/// Stored as `lbt.lock` next to `lbt.toml` and meant to be committed, so everyone building
+/// the project installs exactly the same download for each framework and target. A locked
+/// download whose checksum doesn't match is never installed.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Lock {
+    #[serde(default, rename = "framework", skip_serializing_if = "Vec::is_empty")]
+    pub frameworks: Vec<Locked>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Locked {
+    pub framework: Framework,
+    pub target: Target,
+    /// Release tag the version was resolved to
+    pub version: Version,
+    /// Name of the downloaded asset
+    pub asset: String,
+    /// Where the asset is downloaded from, without mirrors applied
+    pub url: String,
+    /// SHA-256 of the downloaded asset as hex
+    pub sha256: String,
+}
+
+impl Lock {
+    const HEADER: &'static str =
+        "# Generated by lbt, update it with `lbt update` instead of editing it\n\n";
+
+    #[inline]
+    pub fn path(root: &Path) -> std::path::PathBuf {
+        root.join("lbt.lock")
+    }
+
+    /// Parse the `lbt.lock` of the project at `root`, which is empty when it doesn't exist
+    pub fn parse(root: &Path) -> anyhow::Result<Self> {
+        let path = Self::path(root);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        toml::from_str(&std::fs::read_to_string(&path)?)
+            .map_err(|e| anyhow::anyhow!("invalid lock file {}: {e}", path.display()))
+    }
+
+    pub fn parse_or_default() -> anyhow::Result<Self> {
+        Self::parse(&std::env::current_dir()?)
+    }
+
+    pub fn save(&self, root: &Path) -> anyhow::Result<()> {
+        std::fs::write(
+            Self::path(root),
+            format!("{}{}", Self::HEADER, toml::to_string_pretty(self)?),
+        )?;
+        Ok(())
+    }
+
+    /// Download the framework is locked to for the target
+    pub fn get(&self, framework: Framework, target: Target) -> Option<&Locked> {
+        self.frameworks
+            .iter()
+            .find(|locked| locked.framework == framework && locked.target == target)
+    }
+
+    /// Lock a framework's download for a target, replacing what it was locked to before
+    ///
+    /// Returns whether the lock changed
+    pub fn insert(&mut self, locked: Locked) -> bool {
+        if self.get(locked.framework, locked.target) == Some(&locked) {
+            return false;
+        }
+
+        self.frameworks
+            .retain(|other| (other.framework, other.target) != (locked.framework, locked.target));
+        self.frameworks.push(locked);
+        self.frameworks
+            .sort_by_key(|locked| (locked.framework, locked.target));
+        true
+    }
+}
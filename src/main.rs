@@ -1,63 +1,363 @@
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpListener,
+    },
+};
+
 use clap::Parser;
 use lbt::{
-    build::Builder,
-    config::{Build, Config, Framework, Target},
-    git, Version,
+    build::{self, Builder, Executable},
+    config::{Build, Channel, Config, Framework, Target},
+    git,
+    index::Index,
+    lock::Lock,
+    size, Progress, Requirement, Version,
 };
+use strum::IntoEnumIterator;
 
 #[derive(Parser)]
 pub struct LBT {
+    /// Never use the network, frameworks must already be installed
+    ///
+    /// Can also be enabled by setting LBT_OFFLINE. Otherwise responses are reused for an hour,
+    /// or the seconds set by `[cache] ttl` in lbt.toml or LBT_CACHE_TTL
+    #[arg(long, global = true)]
+    offline: bool,
     #[command(subcommand)]
     command: Subcommand,
 }
 
 #[derive(clap::Subcommand)]
 pub enum Subcommand {
-    Build,
-    Run,
+    Build {
+        /// Fail when a version isn't locked in lbt.lock instead of locking it, e.g. in CI
+        #[arg(long)]
+        locked: bool,
+        /// Framework to build when several are configured
+        ///
+        /// Defaults to every framework in lbt.toml
+        #[arg(long)]
+        framework: Option<Framework>,
+        /// Local build of the framework to use instead of `path` or a downloaded version
+        #[arg(long)]
+        framework_path: Option<PathBuf>,
+    },
+    /// Resolve the configured framework versions again and write them to lbt.lock
+    Update,
+    Run {
+        /// Target to run, `web` builds the game and serves it locally
+        ///
+        /// Defaults to the current OS
+        #[arg(long)]
+        target: Option<Target>,
+        /// Port to serve web builds on
+        #[arg(long, default_value_t = 8000)]
+        port: u16,
+        /// Framework to run when several are configured
+        ///
+        /// Defaults to `[run] framework`, or the first framework in lbt.toml
+        #[arg(long)]
+        framework: Option<Framework>,
+        /// Versions of the framework to run with instead of the configured ones, e.g. `0.17.0`
+        ///
+        /// The version isn't added to lbt.lock
+        #[arg(long)]
+        version: Option<Requirement>,
+        /// Local build of the framework to use instead of `path` or a downloaded version
+        #[arg(long)]
+        framework_path: Option<PathBuf>,
+        /// Show the game's output in a console on windows, using `lovec.exe` when love has one
+        #[arg(long)]
+        console: bool,
+        /// Restart the game whenever a file in `src` or a `[run] watch` directory changes
+        #[arg(long)]
+        watch: bool,
+        /// Open love's window where it was the last time the game ran
+        #[arg(long)]
+        keep_window: bool,
+        /// Watch like --watch, but reload changed modules inside of the running love game
+        ///
+        /// Tables of reloaded modules are updated in place so the game keeps it's state, and
+        /// `love.reload(module)` is called afterwards when the game defines it
+        #[arg(long)]
+        live: bool,
+        /// Run the game's fused build for this system, building it first when it's out of date
+        ///
+        /// Catches packaging mistakes that running `src` directly doesn't, e.g. missing assets
+        #[arg(long, alias = "build")]
+        release: bool,
+        /// Arguments passed on to the game, e.g. `lbt run -- --level 2`
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
     Init {
         framework: Framework,
-        version: Option<Version>,
+        /// Versions of the framework to build with, e.g. `11.5`, `^11`, or `latest`
+        ///
+        /// Defaults to versions compatible with the latest release
+        version: Option<Requirement>,
     },
     New {
         name: String,
         framework: Framework,
-        version: Option<Version>,
+        /// Versions of the framework to build with, e.g. `11.5`, `^11`, or `latest`
+        ///
+        /// Defaults to versions compatible with the latest release
+        version: Option<Requirement>,
+    },
+    /// Manage the installed framework runtimes
+    Framework {
+        #[command(subcommand)]
+        command: FrameworkCommand,
     },
     Pass,
 }
 
+#[derive(clap::Subcommand)]
+pub enum FrameworkCommand {
+    /// List the installed framework versions and their size on disk
+    List,
+    /// List the released versions of a framework
+    Available { framework: Framework },
+    /// Install a version of a framework ahead of time
+    Install {
+        framework: Framework,
+        version: Version,
+        /// Targets to install the framework for, can be repeated
+        ///
+        /// Defaults to the current OS
+        #[arg(long)]
+        target: Vec<Target>,
+    },
+    /// Remove an installed version of a framework
+    Remove {
+        framework: Framework,
+        version: Version,
+        /// Target to remove the framework for
+        ///
+        /// Defaults to the current OS
+        #[arg(long)]
+        target: Option<Target>,
+    },
+    /// Remove the installed versions that no known project uses
+    Prune,
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let client = git::Client::new("love-build-tools");
-
+    let args = LBT::parse();
     let mut config = Config::parse_or_default()?;
 
+    let offline = args.offline
+        || std::env::var("LBT_OFFLINE").is_ok_and(|v| !matches!(v.as_str(), "" | "0" | "false"));
+    let mut client = git::Client::new("love-build-tools")
+        .with_offline(offline)
+        .with_mirror(config.mirror.clone().with_env())
+        .with_token(
+            ["GITHUB_TOKEN", "GH_TOKEN"]
+                .into_iter()
+                .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty())),
+        );
+    if let Some(seconds) = config.cache.clone().with_env()?.ttl {
+        client = client.with_ttl(Duration::from_secs(seconds));
+    }
+
+    let cd = std::env::current_dir()?;
+    if cd.join("lbt.toml").exists() {
+        register_project(&cd)?;
+    }
+
+    if let Subcommand::Build {
+        framework,
+        framework_path: Some(path),
+        ..
+    }
+    | Subcommand::Run {
+        framework,
+        framework_path: Some(path),
+        ..
+    } = &args.command
+    {
+        // A build tree is only of one framework
+        let framework = match (framework, config.build.len()) {
+            (Some(framework), _) => *framework,
+            (None, 1) => *config.build.keys().next().unwrap(),
+            (None, 0) => return Err(anyhow::anyhow!("no framework is configured in lbt.toml")),
+            (None, _) => {
+                return Err(anyhow::anyhow!(
+                    "--framework-path needs --framework when several frameworks are configured"
+                ))
+            }
+        };
+        config
+            .build
+            .get_mut(&framework)
+            .ok_or(anyhow::anyhow!(
+                "{framework} is not configured in lbt.toml, add it with `lbt init {framework}`"
+            ))?
+            .path = Some(cd.join(path));
+    }
+
     // TODO: Convert from install command to pull from a config
     #[allow(clippy::single_match)]
-    match LBT::parse().command {
-        Subcommand::Build => {
-            for (framework, build) in config.build.iter() {
-                Builder::new(framework, build, &config)
+    match args.command {
+        Subcommand::Build {
+            locked, framework, ..
+        } => {
+            if let Some(framework) = framework.filter(|key| !config.build.contains_key(key)) {
+                return Err(anyhow::anyhow!(
+                    "{framework} is not configured in lbt.toml, add it with `lbt init {framework}`"
+                ));
+            }
+
+            let lock = Lock::parse(&cd)?;
+            for (framework, build) in config
+                .build
+                .iter()
+                .filter(|(key, _)| framework.is_none_or(|framework| **key == framework))
+            {
+                let version = build::resolve(&client, *framework, build, Some(&lock)).await?;
+                Builder::new(framework, build, version, &config)
+                    .with_locked(locked)
                     .bundle(&client)
                     .await?;
             }
         }
-        Subcommand::Run => {
-            let target = Target::default();
-            if let Some((key, _value)) = config.build.first_key_value() {
-                let exe = key.exe(target);
-                let output = std::process::Command::new(exe.display().to_string())
-                    .arg(
-                        std::env::current_dir()
-                            .unwrap()
-                            .join("src")
-                            .display()
-                            .to_string(),
-                    )
-                    .output()?;
-                std::process::exit(output.status.code().unwrap());
+        Subcommand::Update => update(&client, &config, &cd).await?,
+        Subcommand::Run {
+            target: Some(Target::Web),
+            port,
+            framework,
+            version,
+            console,
+            watch,
+            keep_window,
+            live,
+            release,
+            args,
+            ..
+        } => {
+            if console || watch || keep_window || live || release || !args.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "--console, --watch, --keep-window, --live, --release, and game arguments aren't supported for web builds"
+                ));
+            }
+
+            let pinned = version.is_some();
+            let framework = run_framework(&mut config, framework, version)?;
+            let build = &config.build[&framework];
+
+            let lock = Lock::parse(&cd)?;
+            let version = build::resolve(&client, framework, build, Some(&lock)).await?;
+            let dir = Builder::new(&framework, build, version, &config)
+                .with_update_lock(!pinned)
+                .bundle_target(&client, Target::Web)
+                .await?;
+
+            println!("Serving {} at http://localhost:{port}", dir.display());
+            lbt::server::serve(&dir, port)?;
+        }
+        Subcommand::Run {
+            target,
+            framework,
+            version,
+            console,
+            watch,
+            keep_window,
+            live,
+            release,
+            args,
+            ..
+        } => {
+            let target = match target {
+                Some(target) if target != Target::default() => {
+                    return Err(anyhow::anyhow!(
+                        "{target} builds can't be run on this system"
+                    ))
+                }
+                _ => Target::default(),
+            };
+            let pinned = version.is_some();
+            let framework = run_framework(&mut config, framework, version)?;
+            let build = &config.build[&framework];
+            if (console || keep_window || live) && framework != Framework::Love {
+                return Err(anyhow::anyhow!(
+                    "--console, --keep-window, and --live are only supported for love"
+                ));
+            }
+            // `[run] keep-window` only applies to the frameworks that support it
+            let keep_window = (keep_window
+                || (config.run.keep_window && framework == Framework::Love))
+                && !release;
+            // The fused game is built once, so there is nothing to restart or reload it with
+            if release && (watch || live) {
+                return Err(anyhow::anyhow!(
+                    "--watch and --live can't be used with --release"
+                ));
             }
+
+            let lock = Lock::parse(&cd)?;
+            let version = build::resolve(&client, framework, build, Some(&lock)).await?;
+            let builder = Builder::new(&framework, build, version.clone(), &config)
+                .with_update_lock(!pinned)
+                // The fused build is made from the AppImage or app bundle
+                .with_run(!release);
+            let exe = if release {
+                // Builds of another version can't be told apart from the configured one's
+                if pinned || builder.is_stale(target) {
+                    builder.bundle_target(&client, target).await?;
+                }
+                builder.release_executable(target, console)?
+            } else {
+                let tag = format!("[{framework}:{target}]");
+                let mut spinner = Progress::new(format!("{tag} installing {framework} {version}"));
+                if let Err(err) = builder
+                    .ensure_framework_installed(&client, target, &mut spinner)
+                    .await
+                {
+                    spinner.finish_fail(
+                        format!("{tag} failed to install {framework} {version}").as_str(),
+                    );
+                    return Err(err);
+                }
+                spinner.finish_success(format!("{tag} running {framework} {version}").as_str());
+
+                builder.executable(target, console)?
+            };
+
+            // The agent connects to a port that is free rather than a fixed one
+            let listener = match live {
+                true => Some(TcpListener::bind(("127.0.0.1", 0)).await?),
+                false => None,
+            };
+            let game = Game {
+                exe,
+                root: cd.clone(),
+                keep_window,
+                release,
+                live: match &listener {
+                    Some(listener) => Some(listener.local_addr()?.port()),
+                    None => None,
+                },
+                args,
+            };
+            let code = if watch || live {
+                let mut dirs = vec![cd.join("src")];
+                dirs.extend(config.run.watch.iter().map(|dir| cd.join(dir)));
+                watch_game(&game, &dirs, listener).await?
+            } else {
+                run(game.command()?).await?
+            };
+            std::process::exit(code);
         }
         Subcommand::Init { framework, version } => {
             let dir = std::env::current_dir()?;
@@ -65,11 +365,17 @@ async fn main() -> anyhow::Result<()> {
             config.build.insert(
                 framework,
                 Build {
-                    version: version.unwrap_or(framework.latest()),
+                    version: match version {
+                        Some(version) => version,
+                        None => latest(&client, framework).await,
+                    },
+                    channel: Channel::default(),
+                    path: None,
                     targets: Vec::default(),
                 },
             );
             std::fs::write(dir.join("lbt.toml"), toml::to_string_pretty(&config)?)?;
+            register_project(&dir)?;
         }
         Subcommand::New {
             name,
@@ -88,14 +394,663 @@ async fn main() -> anyhow::Result<()> {
             config.build.insert(
                 framework,
                 Build {
-                    version: version.unwrap_or(framework.latest()),
+                    version: match version {
+                        Some(version) => version,
+                        None => latest(&client, framework).await,
+                    },
+                    channel: Channel::default(),
+                    path: None,
                     targets: Vec::default(),
                 },
             );
             std::fs::write(dir.join("lbt.toml"), toml::to_string_pretty(&config)?)?;
+            register_project(&dir)?;
         }
+        Subcommand::Framework { command } => framework(&client, command).await?,
         _ => {}
     }
 
     Ok(())
 }
+
+/// Framework `lbt run` runs, run with `version` in place of it's configured versions
+///
+/// Defaults to `[run] framework`, or the first configured framework.
+fn run_framework(
+    config: &mut Config,
+    framework: Option<Framework>,
+    version: Option<Requirement>,
+) -> anyhow::Result<Framework> {
+    let framework = match framework.or(config.run.framework) {
+        Some(framework) => framework,
+        None => *config
+            .build
+            .keys()
+            .next()
+            .ok_or(anyhow::anyhow!("no framework is configured in lbt.toml"))?,
+    };
+    let build = config.build.get_mut(&framework).ok_or(anyhow::anyhow!(
+        "{framework} is not configured in lbt.toml, add it with `lbt init {framework}`"
+    ))?;
+
+    if let Some(version) = version {
+        build.version = version;
+    }
+    Ok(framework)
+}
+
+/// Lock every configured framework and target again, replacing `lbt.lock`
+async fn update(client: &git::Client, config: &Config, root: &Path) -> anyhow::Result<()> {
+    let previous = Lock::parse(root)?;
+    let mut lock = Lock::default();
+
+    let mut failed = false;
+    for (framework, build) in config.build.iter() {
+        let targets = if build.targets.is_empty() {
+            vec![Target::default()]
+        } else {
+            build.targets.clone()
+        };
+
+        if let Some(path) = &build.path {
+            Progress::new("").finish_success(
+                format!(
+                    "[{framework}] uses the local build at {}, nothing to lock",
+                    path.display()
+                )
+                .as_str(),
+            );
+            continue;
+        }
+
+        // Resolved once so every target is locked to the same version
+        let version = match build::resolve(client, *framework, build, None).await {
+            Ok(version) => version,
+            Err(err) => {
+                failed = true;
+                Progress::new("").finish_fail(
+                    format!("[{framework}] failed to resolve {}\n  {err}", build.version).as_str(),
+                );
+                continue;
+            }
+        };
+
+        for target in targets {
+            let tag = format!("[{framework}:{target}]");
+            let mut spinner = Progress::new(format!("{tag} resolving {version}"));
+            let locked = async {
+                build::install(client, *framework, &version, target, None, &mut spinner).await?;
+                build::lock_install(client, *framework, &version, target, &mut spinner)
+                    .await?
+                    .ok_or(anyhow::anyhow!(
+                        "where {framework} {version} was downloaded from is unknown"
+                    ))
+            }
+            .await;
+
+            match locked {
+                Ok(locked) => {
+                    let changed = previous.get(*framework, target) != Some(&locked);
+                    spinner.finish_success(
+                        format!(
+                            "{tag} {} {framework} {}",
+                            if changed { "locked" } else { "unchanged" },
+                            locked.version
+                        )
+                        .as_str(),
+                    );
+                    lock.insert(locked);
+                }
+                Err(err) => {
+                    failed = true;
+                    spinner
+                        .finish_fail(format!("{tag} failed to lock {version}\n  {err}").as_str());
+                }
+            }
+        }
+    }
+
+    // A partial lock would drop the versions that failed to resolve
+    if failed {
+        return Err(anyhow::anyhow!("lbt.lock was not updated"));
+    }
+    if lock != previous {
+        lock.save(root)?;
+    }
+    Ok(())
+}
+
+/// How `lbt run` starts the game
+struct Game {
+    exe: Executable,
+    root: PathBuf,
+    /// Run the game packaged with [`WINDOW`] in place of it's `conf.lua`
+    keep_window: bool,
+    /// Port the game's [`LIVE`] agent connects to, which replaces it's `main.lua`
+    live: Option<u16>,
+    /// `exe` is the fused game, which is run without `src`
+    release: bool,
+    args: Vec<String>,
+}
+
+/// `conf.lua` that keeps love's window where it was, see [`Game::package`]
+const WINDOW: &str = include_str!("window.lua");
+/// Where the game's own `conf.lua` is moved to when it's packaged with [`WINDOW`]
+const WINDOW_CONF: &str = ".lbt-conf.lua";
+/// `main.lua` that reloads modules while the game runs, see [`watch_game`]
+const LIVE: &str = include_str!("live.lua");
+/// Where the game's own `main.lua` is moved to when it's packaged with [`LIVE`]
+const LIVE_MAIN: &str = ".lbt-main.lua";
+
+/// How often watched files are checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+/// How long files have to stay the same before the game is restarted
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+/// How long the game gets to quit before it's killed
+const QUIT_TIMEOUT: Duration = Duration::from_secs(3);
+
+impl Game {
+    /// Directory the game's run files are written to
+    fn dir(&self) -> PathBuf {
+        self.root.join("build").join("run")
+    }
+
+    fn command(&self) -> anyhow::Result<tokio::process::Command> {
+        let mut command = self.exe.command();
+        if !self.release {
+            let src = self.root.join("src");
+            command.arg(if self.keep_window || self.live.is_some() {
+                self.package(&src)?
+            } else {
+                src
+            });
+        }
+        command.args(&self.args);
+        Ok(command)
+    }
+
+    /// Package `src` as `build/run/game.love` with [`WINDOW`] as it's `conf.lua` and [`LIVE`]
+    /// as it's `main.lua` when they are used
+    ///
+    /// The game's own files are kept as [`WINDOW_CONF`] and [`LIVE_MAIN`], which the shims run.
+    /// Files are stored rather than compressed since the game is packaged on every restart.
+    fn package(&self, src: &Path) -> anyhow::Result<PathBuf> {
+        let dir = self.dir();
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("game.love");
+
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path)?);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+
+        let mut pending = vec![src.to_path_buf()];
+        while let Some(next) = pending.pop() {
+            for entry in std::fs::read_dir(&next)?.flatten() {
+                let file = entry.path();
+                let name = file
+                    .strip_prefix(src)?
+                    .to_str()
+                    .ok_or(anyhow::anyhow!("{} is not a utf-8 path", file.display()))?
+                    .replace('\\', "/");
+
+                if file.is_dir() {
+                    zip.add_directory(name, options)?;
+                    pending.push(file);
+                    continue;
+                }
+
+                let name = match name.as_str() {
+                    "conf.lua" if self.keep_window => WINDOW_CONF.to_string(),
+                    "main.lua" if self.live.is_some() => LIVE_MAIN.to_string(),
+                    _ => name,
+                };
+                zip.start_file(name, options)?;
+                std::io::copy(&mut std::fs::File::open(&file)?, &mut zip)?;
+            }
+        }
+
+        if self.keep_window {
+            zip.start_file("conf.lua", options)?;
+            zip.write_all(
+                WINDOW
+                    .replace("{{state}}", &dir.join("window").display().to_string())
+                    .replace("{{conf}}", WINDOW_CONF)
+                    .as_bytes(),
+            )?;
+        }
+        if let Some(port) = self.live {
+            zip.start_file("main.lua", options)?;
+            zip.write_all(
+                LIVE.replace("{{port}}", &port.to_string())
+                    .replace("{{main}}", LIVE_MAIN)
+                    .as_bytes(),
+            )?;
+        }
+        zip.finish()?;
+
+        Ok(path)
+    }
+}
+
+/// Run the game with it's output going straight to the terminal, returning it's exit code
+///
+/// Ctrl-C is passed on to the game rather than stopping lbt so the game can shut down on it's
+/// own. A game killed by a signal exits with `128 + signal` like it would in a shell.
+async fn run(command: tokio::process::Command) -> anyhow::Result<i32> {
+    let mut child = spawn(command)?;
+
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => break status?,
+            interrupted = tokio::signal::ctrl_c() => {
+                interrupted?;
+                interrupt(&child);
+            }
+        }
+    };
+    Ok(exit_code(status))
+}
+
+/// Run the game, restarting it whenever a file in `dirs` changes
+///
+/// Bursts of writes, e.g. saving every file at once, restart the game once they settle. The
+/// game keeps being watched after it quits on it's own, and Ctrl-C stops watching it.
+///
+/// When the game is run with the [`LIVE`] agent, which connects to `live`, changed modules are
+/// reloaded by the running game instead, and only other changes restart it.
+async fn watch_game(
+    game: &Game,
+    dirs: &[PathBuf],
+    live: Option<TcpListener>,
+) -> anyhow::Result<i32> {
+    let ignored = game.dir();
+    let src = game.root.join("src");
+    let mut files = snapshot(dirs, &ignored);
+
+    loop {
+        let mut child = Some(spawn(game.command()?)?);
+        let mut agent: Option<(Lines<BufReader<OwnedReadHalf>>, OwnedWriteHalf)> = None;
+
+        loop {
+            tokio::select! {
+                status = async { child.as_mut().unwrap().wait().await }, if child.is_some() => {
+                    println!("Game exited with {}, waiting for changes", exit_code(status?));
+                    child = None;
+                    agent = None;
+                }
+                interrupted = tokio::signal::ctrl_c() => {
+                    interrupted?;
+                    return match child {
+                        Some(mut child) => {
+                            interrupt(&child);
+                            Ok(exit_code(child.wait().await?))
+                        }
+                        None => Ok(130),
+                    };
+                }
+                connected = async { live.as_ref().unwrap().accept().await },
+                    if live.is_some() && agent.is_none() && child.is_some() =>
+                {
+                    let (reader, writer) = connected?.0.into_split();
+                    agent = Some((BufReader::new(reader).lines(), writer));
+                }
+                line = async { agent.as_mut().unwrap().0.next_line().await }, if agent.is_some() => {
+                    match line.ok().flatten() {
+                        Some(line) => report(&line),
+                        None => agent = None,
+                    }
+                }
+                _ = tokio::time::sleep(WATCH_INTERVAL) => {
+                    if snapshot(dirs, &ignored) == files {
+                        continue;
+                    }
+
+                    // Waits until nothing changed for a while, so a burst of writes is handled once
+                    let previous = files;
+                    files = snapshot(dirs, &ignored);
+                    loop {
+                        tokio::time::sleep(WATCH_DEBOUNCE).await;
+                        let current = snapshot(dirs, &ignored);
+                        if current == files {
+                            break;
+                        }
+                        files = current;
+                    }
+
+                    let modules = changed(&previous, &files)
+                        .map(|path| Some((module(&src, path)?, path)))
+                        .collect::<Option<Vec<_>>>();
+                    match (modules, agent.as_mut()) {
+                        (Some(modules), Some((_, writer))) if child.is_some() => {
+                            for (module, path) in modules {
+                                let line = format!("reload\t{module}\t{}\n", path.display());
+                                if writer.write_all(line.as_bytes()).await.is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+            }
+        }
+
+        if let Some(mut child) = child {
+            // Asked to quit first so it can save, [`WINDOW`] also saves while the game runs
+            // since windows can only kill it
+            if !interrupt(&child)
+                || tokio::time::timeout(QUIT_TIMEOUT, child.wait())
+                    .await
+                    .is_err()
+            {
+                // It may have quit in the meantime
+                let _ = child.kill().await;
+            }
+        }
+        println!("Files changed, restarting the game");
+    }
+}
+
+/// Files that were added, changed, or removed between two snapshots
+fn changed<'a>(
+    previous: &'a BTreeMap<PathBuf, (Option<SystemTime>, u64)>,
+    current: &'a BTreeMap<PathBuf, (Option<SystemTime>, u64)>,
+) -> impl Iterator<Item = &'a PathBuf> {
+    current
+        .iter()
+        .filter(|(path, file)| previous.get(*path) != Some(file))
+        .map(|(path, _)| path)
+        .chain(previous.keys().filter(|path| !current.contains_key(*path)))
+}
+
+/// Name a file in `src` is required by, e.g. `levels.forest` for `src/levels/forest.lua`
+///
+/// `main.lua` and `conf.lua` only run when the game starts, so they aren't modules, and
+/// neither are files that were removed.
+fn module(src: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(src).ok()?;
+    if !path.is_file()
+        || path.extension()? != "lua"
+        || relative == Path::new("main.lua")
+        || relative == Path::new("conf.lua")
+    {
+        return None;
+    }
+
+    let mut parts = relative
+        .with_extension("")
+        .components()
+        .map(|part| part.as_os_str().to_str().map(str::to_string))
+        .collect::<Option<Vec<_>>>()?;
+    // `require("levels")` loads `levels/init.lua`
+    if parts.len() > 1 && parts.last().is_some_and(|part| part == "init") {
+        parts.pop();
+    }
+    Some(parts.join("."))
+}
+
+/// Print what the [`LIVE`] agent did with a module
+fn report(line: &str) {
+    let mut parts = line.splitn(3, '\t');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some("reloaded"), Some(module), _) => println!("Reloaded {module}"),
+        (Some("failed"), Some(module), Some(err)) => {
+            println!("Failed to reload {module}, the game kept the old one\n  {err}")
+        }
+        // Modules the game never required are loaded from disk when it does
+        _ => {}
+    }
+}
+
+fn spawn(mut command: tokio::process::Command) -> anyhow::Result<tokio::process::Child> {
+    let program = command.as_std().get_program().to_string_lossy().to_string();
+    command
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("failed to start {program}: {e}"))
+}
+
+/// Ask the game to quit like Ctrl-C would, returning whether it could be asked
+///
+/// Windows sends Ctrl-C to every process of the console, the game included, but there is no
+/// way to send it to the game alone.
+fn interrupt(child: &tokio::process::Child) -> bool {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: sending a signal doesn't touch any memory
+        unsafe { libc::kill(pid as libc::pid_t, libc::SIGINT) };
+        return true;
+    }
+
+    #[cfg(not(unix))]
+    let _ = child;
+    false
+}
+
+/// Exit code of the game, with `128 + signal` when it was killed by a signal like in a shell
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+/// Modification time and size of every file in `dirs`, except for the ones in `ignored`
+fn snapshot(dirs: &[PathBuf], ignored: &Path) -> BTreeMap<PathBuf, (Option<SystemTime>, u64)> {
+    let mut files = BTreeMap::new();
+    let mut pending = dirs.to_vec();
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.metadata() {
+                _ if path.starts_with(ignored) => {}
+                Ok(metadata) if metadata.is_dir() => pending.push(path),
+                Ok(metadata) => {
+                    files.insert(path, (metadata.modified().ok(), metadata.len()));
+                }
+                // Removed while it was being read, which the next snapshot sees
+                Err(_) => {}
+            }
+        }
+    }
+    files
+}
+
+/// Requirement for versions compatible with the latest release of the framework
+///
+/// Falls back to any version when the releases can't be fetched, e.g. while offline
+async fn latest(client: &git::Client, framework: Framework) -> Requirement {
+    let build = Build {
+        version: Requirement::latest(),
+        channel: Channel::Stable,
+        path: None,
+        targets: Vec::new(),
+    };
+    match build::resolve(client, framework, &build, None).await {
+        Ok(version) => Requirement::compatible(&version),
+        Err(_) => Requirement::latest(),
+    }
+}
+
+/// Remember the project so `lbt framework prune` keeps the versions it uses
+fn register_project(root: &Path) -> anyhow::Result<()> {
+    let mut index = Index::load()?;
+    if index.add_project(root) {
+        index.save()?;
+    }
+    Ok(())
+}
+
+async fn framework(client: &git::Client, command: FrameworkCommand) -> anyhow::Result<()> {
+    match command {
+        FrameworkCommand::List => {
+            let mut installed = Index::load()?.installed;
+            if installed.is_empty() {
+                println!("No frameworks are installed");
+            }
+
+            installed.sort_by(|a, b| {
+                (a.framework, &a.version, a.target).cmp(&(b.framework, &b.version, b.target))
+            });
+            for install in installed.iter() {
+                println!(
+                    "{:<6} {:<8} {:<8} {:>10}",
+                    install.framework,
+                    install.version.to_string(),
+                    install.target.to_string(),
+                    size(install.size())
+                );
+            }
+        }
+        FrameworkCommand::Available { framework } => {
+            let index = Index::load()?;
+            let releases = client.releases(framework.owner(), framework.repo()).await?;
+
+            for release in releases.iter().filter(|release| !release.draft) {
+                let targets = Target::iter()
+                    .filter(|target| release.get_platform_asset(*target).is_some())
+                    .map(|target| target.to_string())
+                    .collect::<Vec<_>>();
+                let installed = index
+                    .installed
+                    .iter()
+                    .filter(|install| {
+                        install.framework == framework && install.version == release.tag
+                    })
+                    .map(|install| install.target.to_string())
+                    .collect::<Vec<_>>();
+
+                println!(
+                    "{:<8} {}{}{}",
+                    release.tag.to_string(),
+                    targets.join(", "),
+                    if release.prerelease {
+                        " (prerelease)"
+                    } else {
+                        ""
+                    },
+                    if installed.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" [installed: {}]", installed.join(", "))
+                    }
+                );
+            }
+        }
+        FrameworkCommand::Install {
+            framework,
+            version,
+            target,
+        } => {
+            let targets = if target.is_empty() {
+                vec![Target::default()]
+            } else {
+                target
+            };
+
+            // Pins of the project in the current directory are respected
+            let lock = Lock::parse_or_default()?;
+            let mut failed = false;
+            for target in targets {
+                let tag = format!("[{framework}:{target}]");
+                let mut spinner = Progress::new(format!("{tag} installing {version}"));
+                let locked = lock
+                    .get(framework, target)
+                    .filter(|locked| locked.version == version);
+                match build::install(client, framework, &version, target, locked, &mut spinner)
+                    .await
+                {
+                    Ok(()) => spinner.finish_success(
+                        format!("{tag} {framework} {version} is installed").as_str(),
+                    ),
+                    Err(err) => {
+                        failed = true;
+                        spinner.finish_fail(
+                            format!("{tag} failed to install {version}\n  {err}").as_str(),
+                        );
+                    }
+                }
+            }
+
+            if failed {
+                return Err(anyhow::anyhow!("failed to install {framework} {version}"));
+            }
+        }
+        FrameworkCommand::Remove {
+            framework,
+            version,
+            target,
+        } => {
+            let target = target.unwrap_or_default();
+            let mut index = Index::load()?;
+
+            if index.uninstall(framework, target, &version)?.is_none() {
+                return Err(anyhow::anyhow!(
+                    "{framework} {version} is not installed for {target}"
+                ));
+            }
+            index.save()?;
+            println!("Removed {framework} {version} for {target}");
+        }
+        FrameworkCommand::Prune => {
+            let mut index = Index::load()?;
+
+            // Projects that were moved or deleted no longer keep their versions around
+            index.projects.retain(|root| root.join("lbt.toml").exists());
+
+            // Every version a project could be built with is kept, not only the locked ones
+            let mut used = Vec::new();
+            for root in index.projects.iter() {
+                let config = Config::parse(root).map_err(|e| {
+                    anyhow::anyhow!("failed to read {}: {e}", root.join("lbt.toml").display())
+                })?;
+                let lock = Lock::parse(root)?;
+                for (framework, build) in config.build {
+                    used.push((framework, build.version, build.channel));
+                }
+                for locked in lock.frameworks {
+                    used.push((
+                        locked.framework,
+                        Requirement::from(locked.version),
+                        Channel::Stable,
+                    ));
+                }
+            }
+
+            let unused = index
+                .installed
+                .iter()
+                .filter(|install| {
+                    !used.iter().any(|(framework, requirement, channel)| {
+                        *framework == install.framework
+                            && channel.matches(requirement, &install.version)
+                    })
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+
+            for install in unused.iter() {
+                let size = size(install.size());
+                index.uninstall(install.framework, install.target, &install.version)?;
+                println!(
+                    "Removed {} {} for {} ({size})",
+                    install.framework, install.version, install.target,
+                );
+            }
+            index.save()?;
+
+            if unused.is_empty() {
+                println!("Nothing to prune");
+            }
+        }
+    }
+
+    Ok(())
+}
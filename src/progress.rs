@@ -33,6 +33,18 @@ impl Progress {
         self.0.update_text(msg.as_ref().to_string());
     }
 
+    /// Show how far along a download is, with it's rate in bytes per second
+    pub fn bytes(&mut self, msg: impl AsRef<str>, done: u64, total: Option<u64>, rate: u64) {
+        let total = total.map(|total| format!(" / {}", size(total)));
+        self.update(format!(
+            "{} {}{} ({}/s)",
+            msg.as_ref(),
+            size(done),
+            total.unwrap_or_default(),
+            size(rate)
+        ));
+    }
+
     pub fn finish_success(&mut self, msg: &str) {
         self.0.success(msg);
     }
@@ -41,3 +53,21 @@ impl Progress {
         self.0.fail(msg);
     }
 }
+
+/// Format a size in bytes for display
+pub fn size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} {}", UNITS[0])
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
@@ -0,0 +1,304 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Version;
+
+/// Versions of a framework a project can be built with
+///
+/// Written like cargo's requirements, e.g. `"^11"`, `"~0.17"`, or `">=11.4, <12"`, except that a
+/// bare version such as `"11.5"` is exact rather than compatible versions. `"latest"` matches
+/// every version.
+///
+/// Prereleases like `12.0-dev` only match when a comparator names a prerelease of the same
+/// version, so `">=12.0-dev"` matches `12.0-rc1` but not `12.1-dev`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement(Vec<Comparator>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    major: usize,
+    minor: Option<usize>,
+    patch: Option<usize>,
+    pre: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+}
+
+impl Requirement {
+    pub const fn latest() -> Self {
+        Self(Vec::new())
+    }
+
+    #[inline]
+    pub fn is_latest(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The only version matched, when the requirement is a full version
+    pub fn exact(&self) -> Option<Version> {
+        match self.0.as_slice() {
+            [Comparator {
+                op: Op::Exact,
+                major,
+                minor: Some(minor),
+                patch,
+                pre,
+            }] => Some(Version {
+                major: *major,
+                minor: *minor,
+                patch: *patch,
+                pre: pre.clone(),
+                build: None,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, version: &Version) -> bool {
+        if version.is_prerelease()
+            && !self.0.iter().any(|comparator| {
+                comparator.pre.is_some() && comparator.lower() == version.triple()
+            })
+        {
+            return false;
+        }
+
+        self.matches_prerelease(version)
+    }
+
+    /// Whether `version` matches, letting prereleases of any version match
+    pub fn matches_prerelease(&self, version: &Version) -> bool {
+        self.0.iter().all(|comparator| comparator.matches(version))
+    }
+
+    /// Requirement for versions compatible with `version`, e.g. `^11.5`
+    pub fn compatible(version: &Version) -> Self {
+        Self(vec![Comparator {
+            op: Op::Caret,
+            major: version.major,
+            minor: Some(version.minor),
+            patch: version.patch,
+            pre: version.pre.clone(),
+        }])
+    }
+}
+
+impl From<Version> for Requirement {
+    fn from(version: Version) -> Self {
+        Self(vec![Comparator {
+            op: Op::Exact,
+            major: version.major,
+            minor: Some(version.minor),
+            patch: version.patch,
+            pre: version.pre,
+        }])
+    }
+}
+
+impl Comparator {
+    /// Lowest version the comparator's parts describe
+    fn lower(&self) -> (usize, usize, usize) {
+        (self.major, self.minor.unwrap_or(0), self.patch.unwrap_or(0))
+    }
+
+    /// Lowest version above every version the comparator's parts describe
+    fn upper(&self) -> (usize, usize, usize) {
+        match (self.minor, self.patch) {
+            (None, _) => (self.major + 1, 0, 0),
+            (Some(minor), None) => (self.major, minor + 1, 0),
+            (Some(minor), Some(patch)) => (self.major, minor, patch + 1),
+        }
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        // Missing patches are the same as `.0`, so `11.5` is `11.5.0`
+        let v = version.triple();
+
+        // A prerelease orders before it's version, so it's compared as a whole version
+        if let Some(pre) = &self.pre {
+            let lower = Version {
+                major: self.major,
+                minor: self.minor.unwrap_or(0),
+                patch: self.patch,
+                pre: Some(pre.clone()),
+                build: None,
+            };
+            match self.op {
+                Op::Exact => return *version == lower,
+                Op::Greater => return *version > lower,
+                Op::Less => return *version < lower,
+                Op::LessEq => return *version <= lower,
+                _ if *version < lower => return false,
+                _ => {}
+            }
+        }
+
+        match self.op {
+            Op::Exact => self.lower() <= v && v < self.upper(),
+            Op::Greater => v >= self.upper(),
+            Op::GreaterEq => v >= self.lower(),
+            Op::Less => v < self.lower(),
+            Op::LessEq => v < self.upper(),
+            Op::Tilde => {
+                let upper = match self.minor {
+                    None => (self.major + 1, 0, 0),
+                    Some(minor) => (self.major, minor + 1, 0),
+                };
+                self.lower() <= v && v < upper
+            }
+            Op::Caret => {
+                // Only the left most part that isn't zero has to match
+                let upper = match (self.major, self.minor, self.patch) {
+                    (0, Some(0), Some(patch)) => (0, 0, patch + 1),
+                    (0, Some(minor), _) => (0, minor + 1, 0),
+                    (major, _, _) => (major + 1, 0, 0),
+                };
+                self.lower() <= v && v < upper
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Requirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_latest() {
+            return write!(f, "latest");
+        }
+
+        for (i, comparator) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{comparator}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Comparator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self.op {
+            Op::Exact => "",
+            Op::Greater => ">",
+            Op::GreaterEq => ">=",
+            Op::Less => "<",
+            Op::LessEq => "<=",
+            Op::Tilde => "~",
+            Op::Caret => "^",
+        };
+        write!(f, "{op}{}", self.major)?;
+        if let Some(minor) = self.minor {
+            write!(f, ".{minor}")?;
+        }
+        if let Some(patch) = self.patch {
+            write!(f, ".{patch}")?;
+        }
+        if let Some(pre) = &self.pre {
+            write!(f, "-{pre}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Requirement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if matches!(s, "latest" | "*" | "") {
+            return Ok(Self::latest());
+        }
+
+        s.split(',')
+            .map(|comparator| comparator.trim().parse::<Comparator>())
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+}
+
+impl FromStr for Comparator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (op, version) = [
+            (">=", Op::GreaterEq),
+            ("<=", Op::LessEq),
+            (">", Op::Greater),
+            ("<", Op::Less),
+            ("=", Op::Exact),
+            ("^", Op::Caret),
+            ("~", Op::Tilde),
+        ]
+        .into_iter()
+        .find_map(|(prefix, op)| s.strip_prefix(prefix).map(|rest| (op, rest)))
+        .unwrap_or((Op::Exact, s));
+
+        let version = version.trim();
+        let version = version.strip_prefix('v').unwrap_or(version);
+        // Build metadata doesn't change which versions match
+        let version = version
+            .split_once('+')
+            .map_or(version, |(version, _)| version);
+        let (version, pre) = match version.split_once('-') {
+            Some((version, pre)) if !pre.is_empty() => (version, Some(pre.to_string())),
+            Some(_) => return Err(format!("invalid version requirement: {s}")),
+            None => (version, None),
+        };
+
+        // Wildcards like `11.x` are the same as leaving the part out
+        let mut parts = version
+            .split('.')
+            .take_while(|part| !matches!(*part, "x" | "X" | "*"))
+            .map(|part| {
+                part.parse::<usize>()
+                    .map_err(|_| format!("invalid version requirement: {s}"))
+            });
+
+        let major = parts
+            .next()
+            .ok_or(format!("invalid version requirement: {s}"))??;
+        let minor = parts.next().transpose()?;
+        let patch = parts.next().transpose()?;
+        if parts.next().is_some() {
+            return Err(format!("invalid version requirement: {s}"));
+        }
+
+        Ok(Self {
+            op,
+            major,
+            minor,
+            patch,
+            pre,
+        })
+    }
+}
+
+impl Serialize for Requirement {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Requirement {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        Requirement::from_str(&value).map_err(serde::de::Error::custom)
+    }
+}
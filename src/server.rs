@@ -0,0 +1,148 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Component, Path, PathBuf},
+};
+
+/// Serve the files in `root` over http on localhost until the process is stopped
+///
+/// Responses are sent with cross-origin isolation headers so the threaded love.js runtime
+/// can use `SharedArrayBuffer`, and are never cached so a rebuild shows up on reload.
+pub fn serve(root: &Path, port: u16) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|e| anyhow::anyhow!("failed to listen on port {port}: {e}"))?;
+
+    for stream in listener.incoming().flatten() {
+        let root = root.to_path_buf();
+        std::thread::spawn(move || {
+            // Dropped connections are expected from browsers, there is nothing to report
+            let _ = respond(&root, stream);
+        });
+    }
+
+    Ok(())
+}
+
+fn respond(root: &Path, mut stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Headers aren't used but must be read before responding
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"));
+    if method != "GET" && method != "HEAD" {
+        return write_response(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"",
+            true,
+        );
+    }
+
+    let file = resolve(root, target).filter(|file| file.is_file());
+    let Some(file) = file else {
+        return write_response(
+            &mut stream,
+            "404 Not Found",
+            "text/plain",
+            b"not found",
+            true,
+        );
+    };
+
+    let content = std::fs::read(&file)?;
+    write_response(
+        &mut stream,
+        "200 OK",
+        content_type(&file),
+        &content,
+        method == "GET",
+    )
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+    include_body: bool,
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: {content_type}\r\n\
+         Content-Length: {}\r\n\
+         Cache-Control: no-store\r\n\
+         Cross-Origin-Opener-Policy: same-origin\r\n\
+         Cross-Origin-Embedder-Policy: require-corp\r\n\
+         Connection: close\r\n\r\n",
+        body.len()
+    )?;
+    if include_body {
+        stream.write_all(body)?;
+    }
+    stream.flush()
+}
+
+/// Map the request target to a file inside of `root`, rejecting paths that leave it
+fn resolve(root: &Path, target: &str) -> Option<PathBuf> {
+    let path = decode(target.split(['?', '#']).next().unwrap_or("/"))?;
+    let path = path.trim_start_matches('/');
+    let path = if path.is_empty() || path.ends_with('/') {
+        format!("{path}index.html")
+    } else {
+        path.to_string()
+    };
+
+    let relative = Path::new(&path);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    Some(root.join(relative))
+}
+
+/// Percent decode a url path
+fn decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+fn content_type(file: &Path) -> &'static str {
+    match file.extension().and_then(|v| v.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "text/javascript",
+        // Needed for browsers to compile the runtime while it downloads
+        Some("wasm") => "application/wasm",
+        Some("css") => "text/css",
+        Some("json") => "application/json",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        Some("svg") => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+}
@@ -1,12 +1,20 @@
-use std::str::FromStr;
+use std::{cmp::Ordering, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// Version of a framework or project, e.g. `11.5`, `0.18.0-beta`, or `12.0-dev+a1b2c3`
+///
+/// Versions are ordered like semver, so a missing patch is the same as `.0` and a prerelease
+/// comes before it's release. Build metadata is kept but doesn't take part in comparisons.
+#[derive(Debug, Clone)]
 pub struct Version {
     pub major: usize,
     pub minor: usize,
     pub patch: Option<usize>,
+    /// Prerelease identifiers after the `-`, e.g. `beta.2`
+    pub pre: Option<String>,
+    /// Build metadata after the `+`
+    pub build: Option<String>,
 }
 
 impl Version {
@@ -15,6 +23,8 @@ impl Version {
             major: 11,
             minor: 0,
             patch: None,
+            pre: None,
+            build: None,
         }
     }
 
@@ -23,38 +33,109 @@ impl Version {
             major: 0,
             minor: 15,
             patch: Some(0),
+            pre: None,
+            build: None,
         }
     }
 
-    pub const fn latest_love_version() -> Version {
-        Version {
-            major: 11,
-            minor: 5,
-            patch: None,
+    #[inline]
+    pub const fn is_prerelease(&self) -> bool {
+        self.pre.is_some()
+    }
+
+    /// Id of the workflow run that built a nightly version, e.g. `12.0-nightly.<run>+<commit>`
+    pub fn nightly_run(&self) -> Option<u64> {
+        self.pre.as_deref()?.strip_prefix("nightly.")?.parse().ok()
+    }
+
+    #[inline]
+    pub fn is_nightly(&self) -> bool {
+        self.nightly_run().is_some()
+    }
+
+    /// Major, minor, and patch of the version with a missing patch as `0`
+    #[inline]
+    pub fn triple(&self) -> (usize, usize, usize) {
+        (self.major, self.minor, self.patch.unwrap_or(0))
+    }
+
+    /// Version written the same for every version it's equal to, e.g. `11.5.0` for `11.5`
+    ///
+    /// Used where versions name something, like the directory a version is installed to.
+    pub fn normalized(&self) -> String {
+        let (major, minor, patch) = self.triple();
+        match &self.pre {
+            Some(pre) => format!("{major}.{minor}.{patch}-{pre}"),
+            None => format!("{major}.{minor}.{patch}"),
         }
     }
+}
 
-    pub const fn latest_lovr_version() -> Version {
-        Version {
-            major: 0,
-            minor: 17,
-            patch: Some(0),
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.triple()
+            .cmp(&other.triple())
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => compare_pre(a, b),
+            })
+    }
+}
+
+/// Compare prerelease identifiers one by one
+///
+/// Numeric identifiers are compared as numbers and come before alphanumeric ones, and when
+/// every identifier is equal the one with more identifiers is greater.
+fn compare_pre(a: &str, b: &str) -> Ordering {
+    let mut a = a.split('.');
+    let mut b = b.split('.');
+    loop {
+        let ordering = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => a.cmp(b),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
         }
     }
 }
 
 impl std::fmt::Display for Version {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}.{}{}",
-            self.major,
-            self.minor,
-            match self.patch {
-                Some(v) => format!(".{v}"),
-                None => String::new(),
-            }
-        )
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if let Some(patch) = self.patch {
+            write!(f, ".{patch}")?;
+        }
+        if let Some(pre) = &self.pre {
+            write!(f, "-{pre}")?;
+        }
+        if let Some(build) = &self.build {
+            write!(f, "+{build}")?;
+        }
+        Ok(())
     }
 }
 
@@ -74,12 +155,7 @@ impl<'de> Deserialize<'de> for Version {
     {
         let value = String::deserialize(deserializer)?;
 
-        Version::from_str(if value.starts_with("v") {
-            &value[1..]
-        } else {
-            value.as_str()
-        })
-        .map_err(serde::de::Error::custom)
+        Version::from_str(&value).map_err(serde::de::Error::custom)
     }
 }
 
@@ -87,23 +163,47 @@ impl FromStr for Version {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.splitn(3, '.');
+        let invalid = || format!("invalid version: {s}");
+        let value = s.trim();
+        let value = value.strip_prefix('v').unwrap_or(value);
 
-        Ok(Self {
-            major: parts
-                .next()
-                .unwrap_or("0")
-                .parse::<usize>()
-                .map_err(|v| v.to_string())?,
-            minor: parts
+        let (value, build) = match value.split_once('+') {
+            Some((value, build)) => (value, Some(identifiers(build).ok_or_else(invalid)?)),
+            None => (value, None),
+        };
+        let (value, pre) = match value.split_once('-') {
+            Some((value, pre)) => (value, Some(identifiers(pre).ok_or_else(invalid)?)),
+            None => (value, None),
+        };
+
+        let mut parts = value.splitn(3, '.');
+        let mut part = |default: Option<&str>| {
+            parts
                 .next()
-                .unwrap_or("0")
-                .parse::<usize>()
-                .map_err(|v| v.to_string())?,
-            patch: match parts.next() {
-                Some(patch) => Some(patch.parse::<usize>().map_err(|e| e.to_string())?),
-                None => None,
-            },
+                .or(default)
+                .map(|part| part.parse::<usize>().map_err(|_| invalid()))
+                .transpose()
+        };
+
+        Ok(Self {
+            major: part(None)?.ok_or_else(invalid)?,
+            minor: part(Some("0"))?.unwrap_or(0),
+            patch: part(None)?,
+            pre,
+            build,
         })
     }
 }
+
+/// Dot separated identifiers of a prerelease or build metadata, if they are valid
+fn identifiers(value: &str) -> Option<String> {
+    value
+        .split('.')
+        .all(|identifier| {
+            !identifier.is_empty()
+                && identifier
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        .then(|| value.to_string())
+}
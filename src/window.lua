@@ -0,0 +1,86 @@
+-- Written by `lbt run --keep-window` in place of the game's conf.lua, which is moved to
+-- {{conf}}, so the window opens where it was before the game was restarted
+local state = [==[{{state}}]==]
+
+if love.filesystem.getInfo("{{conf}}") then
+    love.filesystem.load("{{conf}}")()
+end
+
+local saved
+local function save()
+    if not (love.window and love.window.isOpen()) then
+        return
+    end
+
+    local x, y, display = love.window.getPosition()
+    local position = x .. " " .. y .. " " .. display
+    if position == saved then
+        return
+    end
+
+    local file = io.open(state, "w")
+    if file then
+        file:write(position)
+        file:close()
+        saved = position
+    end
+end
+
+local conf = love.conf
+function love.conf(t)
+    if conf then
+        conf(t)
+    end
+
+    local file = io.open(state, "r")
+    if file and type(t.window) == "table" then
+        local x, y, display = file:read("*n", "*n", "*n")
+        if x and y then
+            t.window.x, t.window.y = x, y
+            t.window.display = display or t.window.display
+        end
+    end
+    if file then
+        file:close()
+    end
+end
+
+-- The position is also saved while the game runs, since lbt can't always ask the game to quit
+-- before restarting it, e.g. on windows where it's killed
+local INTERVAL = 0.5
+local checked = 0
+
+-- main.lua defines the callbacks after this runs, so they're wrapped when they're assigned
+local callbacks = {}
+local wrappers = {
+    quit = function(...)
+        local abort = callbacks.quit and callbacks.quit(...)
+        if not abort then
+            save()
+        end
+        return abort
+    end,
+    update = function(...)
+        local now = love.timer and love.timer.getTime() or os.clock()
+        if now - checked >= INTERVAL then
+            checked = now
+            save()
+        end
+        if callbacks.update then
+            return callbacks.update(...)
+        end
+    end,
+}
+
+setmetatable(love, {
+    __index = function(_, key)
+        return wrappers[key]
+    end,
+    __newindex = function(t, key, value)
+        if wrappers[key] then
+            callbacks[key] = value
+        else
+            rawset(t, key, value)
+        end
+    end,
+})